@@ -37,14 +37,8 @@ fn main() -> anyhow::Result<()> {
     let tokens = client.exchange_code(code, &flow.pkce_verifier)?;
 
     println!("\n✅ Success!");
-    println!(
-        "Access token: {}...",
-        &tokens.access_token[..30.min(tokens.access_token.len())]
-    );
-    println!(
-        "Refresh token: {}...",
-        &tokens.refresh_token[..30.min(tokens.refresh_token.len())]
-    );
+    println!("Access token: {}", tokens.masked_access_token());
+    println!("Refresh token: {}", tokens.masked_refresh_token());
     println!("Expires in: {:?}", tokens.expires_in());
 
     // Step 4: Extract account ID from JWT
@@ -38,10 +38,7 @@ fn main() -> anyhow::Result<()> {
     let tokens = client.exchange_code(code, &flow.pkce_verifier)?;
 
     println!("\n✅ Success!");
-    println!(
-        "Access token: {}...",
-        &tokens.access_token[..30.min(tokens.access_token.len())]
-    );
+    println!("Access token: {}", tokens.masked_access_token());
     println!("Expires in: {:?}", tokens.expires_in());
 
     if let Ok(account_id) = client.extract_account_id(&tokens.access_token) {
@@ -44,10 +44,7 @@ async fn main() -> Result<()> {
     let tokens = client.exchange_code(&code, &flow.pkce_verifier).await?;
 
     println!("\n✅ Success!");
-    println!(
-        "Access token: {}...",
-        &tokens.access_token[..30.min(tokens.access_token.len())]
-    );
+    println!("Access token: {}", tokens.masked_access_token());
     println!("Expires in: {:?}", tokens.expires_in());
 
     if let Ok(account_id) = client.extract_account_id(&tokens.access_token) {
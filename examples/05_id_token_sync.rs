@@ -30,12 +30,9 @@ fn main() -> anyhow::Result<()> {
     let tokens = client.exchange_code_for_api_key(code, &flow.pkce_verifier)?;
 
     println!("\n✅ Success!");
-    println!(
-        "Access token: {}...",
-        &tokens.access_token[..30.min(tokens.access_token.len())]
-    );
-    if let Some(api_key) = tokens.api_key.as_deref() {
-        println!("API key: {}...", &api_key[..30.min(api_key.len())]);
+    println!("Access token: {}", tokens.masked_access_token());
+    if let Some(api_key) = tokens.masked_api_key() {
+        println!("API key: {}", api_key);
     }
     println!("Expires in: {:?}", tokens.expires_in());
 
@@ -8,7 +8,8 @@
 //! Run with: cargo run --example 06_callback_custom_html --features full
 
 use openai_auth::{
-    CallbackEvent, OAuthClient, OAuthConfig, Result, open_browser, run_callback_server_with_html,
+    CallbackEvent, OAuthClient, OAuthConfig, Result, escape_html, open_browser,
+    run_callback_server_with_html,
 };
 
 #[tokio::main]
@@ -42,7 +43,7 @@ async fn main() -> Result<()> {
                 </body>
             </html>
             "#,
-            reason
+            escape_html(&reason)
         ),
         CallbackEvent::StateMismatch => r#"
             <html>
@@ -84,12 +85,9 @@ async fn main() -> Result<()> {
     let tokens = client.exchange_code(&code, &flow.pkce_verifier).await?;
 
     println!("\n✅ Success!");
-    println!(
-        "Access token: {}...",
-        &tokens.access_token[..30.min(tokens.access_token.len())]
-    );
-    if let Some(api_key) = tokens.api_key.as_deref() {
-        println!("API key: {}...", &api_key[..30.min(api_key.len())]);
+    println!("Access token: {}", tokens.masked_access_token());
+    if let Some(api_key) = tokens.masked_api_key() {
+        println!("API key: {}", api_key);
     }
     println!("Expires in: {:?}", tokens.expires_in());
 
@@ -0,0 +1,214 @@
+#[cfg(any(feature = "async", feature = "blocking"))]
+use crate::Result;
+use crate::TokenSet;
+
+/// Base URL of the OpenAI API, used when an API key is available
+pub const OPENAI_API_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// Base URL of the ChatGPT backend, used with a ChatGPT access token
+pub const CHATGPT_API_BASE_URL: &str = "https://chatgpt.com/backend-api/codex";
+
+/// Everything needed to configure an OpenAI API client
+///
+/// Created with `TokenSet::into_api_config`. If the tokens include an API
+/// key, it targets the public OpenAI API; otherwise it uses the ChatGPT
+/// access token together with the `chatgpt-account-id` header.
+///
+/// **Note:** This requires the `openai-client` feature.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiConfig {
+    /// Base URL for API requests
+    pub base_url: String,
+    /// Token to send as `Authorization: Bearer <token>`
+    pub bearer_token: String,
+    /// ChatGPT account ID to send as the `chatgpt-account-id` header, if any
+    pub account_id: Option<String>,
+}
+
+impl ApiConfig {
+    /// The headers to send with every API request
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![("Authorization", format!("Bearer {}", self.bearer_token))];
+        if let Some(account_id) = &self.account_id {
+            headers.push(("chatgpt-account-id", account_id.clone()));
+        }
+        headers
+    }
+}
+
+impl std::fmt::Debug for ApiConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiConfig")
+            .field("base_url", &self.base_url)
+            .field("bearer_token", &"<redacted>")
+            .field("account_id", &self.account_id)
+            .finish()
+    }
+}
+
+impl TokenSet {
+    /// Bundle the base URL, bearer token and account ID for an API client
+    ///
+    /// **Note:** This requires the `openai-client` feature.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn example(tokens: openai_auth::TokenSet) {
+    /// let api = tokens.into_api_config();
+    /// for (name, value) in api.headers() {
+    ///     println!("{}: {}", name, value);
+    /// }
+    /// # }
+    /// ```
+    pub fn into_api_config(self) -> ApiConfig {
+        match self.api_key {
+            Some(api_key) => ApiConfig {
+                base_url: OPENAI_API_BASE_URL.to_string(),
+                bearer_token: api_key,
+                account_id: None,
+            },
+            None => ApiConfig {
+                base_url: CHATGPT_API_BASE_URL.to_string(),
+                account_id: crate::jwt::extract_account_id_from_tokens(&self).ok(),
+                bearer_token: self.access_token,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl crate::OAuthClient {
+    /// Refresh `tokens` if needed and attach the API headers to `request`
+    ///
+    /// Calls `ensure_fresh` and then adds the `Authorization` and, for
+    /// ChatGPT tokens, `chatgpt-account-id` headers from `into_api_config`.
+    /// Call it before sending each request so every API call is
+    /// authenticated with a valid token, or use `OpenAiAuthMiddleware`
+    /// (`middleware` feature) to do it for a `reqwest-middleware` client.
+    ///
+    /// **Note:** This requires the `openai-client` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tokens need a refresh and it fails. The
+    /// request is not sent in that case.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use openai_auth::{OAuthClient, OAuthConfig, TokenSet};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let client = OAuthClient::new(OAuthConfig::default())?;
+    /// # let mut tokens = TokenSet::builder("access", 0).refresh_token("refresh").build();
+    /// let http = reqwest::Client::new();
+    /// let request = http.get("https://chatgpt.com/backend-api/codex/models");
+    /// let response = client
+    ///     .authenticate_request(&mut tokens, request)
+    ///     .await?
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn authenticate_request(
+        &self,
+        tokens: &mut TokenSet,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::RequestBuilder> {
+        self.ensure_fresh(tokens).await?;
+        let headers = tokens.clone().into_api_config().headers();
+        Ok(headers.into_iter().fold(request, |request, (name, value)| {
+            request.header(name, value)
+        }))
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl crate::blocking::OAuthClient {
+    /// Refresh `tokens` if needed and attach the API headers to `request`
+    ///
+    /// This is the blocking version of `authenticate_request`.
+    ///
+    /// **Note:** This requires the `openai-client` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tokens need a refresh and it fails
+    pub fn authenticate_request(
+        &self,
+        tokens: &mut TokenSet,
+        request: reqwest::blocking::RequestBuilder,
+    ) -> Result<reqwest::blocking::RequestBuilder> {
+        self.ensure_fresh(tokens)?;
+        let headers = tokens.clone().into_api_config().headers();
+        Ok(headers.into_iter().fold(request, |request, (name, value)| {
+            request.header(name, value)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_api_config_prefers_api_key() {
+        let mut tokens = TokenSet::builder("not-a-jwt", 0)
+            .refresh_token("refresh")
+            .api_key("sk-test")
+            .build();
+
+        let api = tokens.clone().into_api_config();
+        assert_eq!(api.base_url, OPENAI_API_BASE_URL);
+        assert_eq!(
+            api.headers(),
+            [("Authorization", "Bearer sk-test".to_string())]
+        );
+        assert!(!format!("{:?}", api).contains("sk-test"));
+
+        tokens.api_key = None;
+        let api = tokens.clone().into_api_config();
+        assert_eq!(api.base_url, CHATGPT_API_BASE_URL);
+        assert_eq!(api.bearer_token, "not-a-jwt");
+        assert_eq!(api.account_id, None);
+
+        // The account ID may only be in the id_token
+        tokens.id_token = Some(crate::testing::unsigned_jwt(
+            r#"{"exp":1,"https://api.openai.com/auth":{"chatgpt_account_id":"acct-1"}}"#,
+        ));
+        let api = tokens.into_api_config();
+        assert_eq!(api.account_id.as_deref(), Some("acct-1"));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_authenticate_request_adds_headers_or_fails() {
+        let client = crate::OAuthClient::default();
+        let access_token = crate::testing::unsigned_jwt(
+            r#"{"exp":1,"https://api.openai.com/auth":{"chatgpt_account_id":"acct-1"}}"#,
+        );
+        let mut tokens = TokenSet::builder(access_token.clone(), u64::MAX / 2).build();
+
+        let http = reqwest::Client::new();
+        let request = client
+            .authenticate_request(&mut tokens, http.get("http://127.0.0.1/models"))
+            .await
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.headers()["authorization"],
+            format!("Bearer {}", access_token).as_str()
+        );
+        assert_eq!(request.headers()["chatgpt-account-id"], "acct-1");
+
+        // Expired tokens that can't be refreshed surface the refresh error
+        tokens.expires_at = 0;
+        let result = client
+            .authenticate_request(&mut tokens, http.get("http://127.0.0.1/models"))
+            .await;
+        assert!(matches!(result, Err(crate::OpenAIAuthError::OAuth(_))));
+    }
+}
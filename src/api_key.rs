@@ -0,0 +1,175 @@
+use std::sync::Mutex;
+
+#[cfg(any(feature = "async", feature = "blocking"))]
+use serde::Deserialize;
+
+use crate::OrgInfo;
+#[cfg(any(feature = "async", feature = "blocking"))]
+use crate::{OpenAIAuthError, Result};
+
+/// Lazily obtained, cached OpenAI API key
+///
+/// Pass this to `OAuthClient::api_key_cached` to exchange the id_token for
+/// an API key only when it's first needed. The key is cached per id_token:
+/// once the tokens are refreshed and carry a new id_token, the next access
+/// exchanges it again.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "async")]
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use openai_auth::{LazyApiKey, OAuthClient, OAuthConfig, TokenSet};
+///
+/// # let tokens = TokenSet::builder("access", 0).build();
+/// let client = OAuthClient::new(OAuthConfig::default())?;
+/// let api_key = LazyApiKey::new();
+///
+/// // Only the first call performs the exchange
+/// let key = client.api_key_cached(&api_key, &tokens).await?;
+/// let same_key = client.api_key_cached(&api_key, &tokens).await?;
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "async"))]
+/// # fn main() {}
+/// ```
+#[derive(Debug, Default)]
+pub struct LazyApiKey {
+    // (id_token the key was obtained from, api key)
+    cached: Mutex<Option<(String, String)>>,
+}
+
+impl LazyApiKey {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the cached API key, if one has been obtained
+    pub fn cached(&self) -> Option<String> {
+        self.cached
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|(_, key)| key.clone())
+    }
+
+    /// Forget the cached API key so the next access exchanges again
+    pub fn clear(&self) {
+        *self.cached.lock().unwrap() = None;
+    }
+
+    /// The cached key if it was obtained from `id_token`
+    #[cfg(any(feature = "async", feature = "blocking"))]
+    pub(crate) fn get_for(&self, id_token: &str) -> Option<String> {
+        match &*self.cached.lock().unwrap() {
+            Some((cached_id_token, key)) if cached_id_token == id_token => Some(key.clone()),
+            _ => None,
+        }
+    }
+
+    #[cfg(any(feature = "async", feature = "blocking"))]
+    pub(crate) fn store(&self, id_token: &str, key: &str) {
+        *self.cached.lock().unwrap() = Some((id_token.to_string(), key.to_string()));
+    }
+}
+
+/// The account an OpenAI API key belongs to
+///
+/// Returned by `OAuthClient::describe_api_key`, e.g. to show which account
+/// a pasted API key is for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKeyInfo {
+    /// The user ID (`user-...`)
+    pub user_id: String,
+    /// The user's email address, if returned
+    pub email: Option<String>,
+    /// The user's display name, if returned
+    pub name: Option<String>,
+    /// The organizations the key's user belongs to
+    pub organizations: Vec<OrgInfo>,
+}
+
+impl ApiKeyInfo {
+    /// Parse a successful response body of the user info endpoint
+    #[cfg(any(feature = "async", feature = "blocking"))]
+    pub(crate) fn from_body(
+        status: u16,
+        body: &str,
+        request_id: Option<String>,
+    ) -> Result<ApiKeyInfo> {
+        #[derive(Deserialize)]
+        struct Me {
+            id: String,
+            #[serde(default)]
+            email: Option<String>,
+            #[serde(default)]
+            name: Option<String>,
+            #[serde(default)]
+            orgs: Option<Orgs>,
+        }
+        #[derive(Deserialize)]
+        struct Orgs {
+            #[serde(default)]
+            data: Vec<OrgInfo>,
+        }
+
+        let me: Me = crate::types::parse_json(body, |body| OpenAIAuthError::Http {
+            status,
+            error_code: None,
+            body,
+            request_id,
+        })?;
+        Ok(ApiKeyInfo {
+            user_id: me.id,
+            email: me.email,
+            name: me.name,
+            organizations: me.orgs.map(|orgs| orgs.data).unwrap_or_default(),
+        })
+    }
+}
+
+/// Check that `api_key` looks like an API key before sending it anywhere
+#[cfg(any(feature = "async", feature = "blocking"))]
+pub(crate) fn check_api_key_format(api_key: &str) -> Result<()> {
+    match crate::classify_credential(api_key) {
+        crate::CredentialKind::ApiKey => Ok(()),
+        _ => Err(OpenAIAuthError::InvalidApiKey(
+            "not an OpenAI API key (expected sk-...)".to_string(),
+        )),
+    }
+}
+
+/// Map a failed user info response to an error
+///
+/// A 401 means the key itself was rejected and becomes `InvalidApiKey`
+/// with the API's error message; anything else is reported as `Http`.
+#[cfg(any(feature = "async", feature = "blocking"))]
+pub(crate) fn describe_error(
+    status: u16,
+    body: String,
+    request_id: Option<String>,
+) -> OpenAIAuthError {
+    #[derive(Deserialize)]
+    struct ApiError {
+        error: ApiErrorBody,
+    }
+    #[derive(Deserialize)]
+    struct ApiErrorBody {
+        message: String,
+    }
+
+    if status == 401 {
+        let message = serde_json::from_str::<ApiError>(&body)
+            .map(|e| e.error.message)
+            .unwrap_or(body);
+        return OpenAIAuthError::InvalidApiKey(message);
+    }
+    OpenAIAuthError::Http {
+        status,
+        error_code: None,
+        body,
+        request_id,
+    }
+}
@@ -0,0 +1,300 @@
+use url::Url;
+
+use crate::types::{
+    authorization_params, generate_pkce_pair, generate_random_state, pkce_challenge,
+    validate_verifier,
+};
+use crate::{OAuthConfig, OAuthFlow, Result};
+
+/// Builder for authorization URLs, independent of the HTTP client
+///
+/// Produces the same URLs as `OAuthClient::start_flow`, with optional
+/// per-flow overrides. Use it when the code exchange happens elsewhere
+/// (e.g. on a server or in another language) and only the URL logic is
+/// needed.
+///
+/// # Example
+///
+/// ```
+/// use openai_auth::{AuthorizationUrlBuilder, OAuthConfig};
+///
+/// let config = OAuthConfig::default();
+/// let flow = AuthorizationUrlBuilder::new(&config)
+///     .login_hint("user@example.com")
+///     .param("audience", "https://api.openai.com/v1")
+///     .build()?;
+/// println!("Visit: {}", flow.authorization_url);
+/// # Ok::<(), openai_auth::OpenAIAuthError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct AuthorizationUrlBuilder<'a> {
+    config: &'a OAuthConfig,
+    scopes: Option<Vec<String>>,
+    prompt: Option<String>,
+    login_hint: Option<String>,
+    extra_params: Vec<(String, String)>,
+    state: Option<String>,
+    verifier: Option<String>,
+}
+
+impl<'a> AuthorizationUrlBuilder<'a> {
+    /// Create a builder for the given configuration
+    pub fn new(config: &'a OAuthConfig) -> Self {
+        Self {
+            config,
+            scopes: None,
+            prompt: None,
+            login_hint: None,
+            extra_params: Vec::new(),
+            state: None,
+            verifier: None,
+        }
+    }
+
+    /// Request these scopes (default: the configured `scopes`)
+    pub fn scopes(mut self, scopes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.scopes = Some(scopes.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set the `prompt` parameter (e.g. "none" or "login")
+    pub fn prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = Some(prompt.into());
+        self
+    }
+
+    /// Set the `login_hint` parameter to pre-fill the user's email
+    pub fn login_hint(mut self, login_hint: impl Into<String>) -> Self {
+        self.login_hint = Some(login_hint.into());
+        self
+    }
+
+    /// Add an extra query parameter
+    ///
+    /// Can be called multiple times.
+    pub fn param(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra_params.push((key.into(), value.into()));
+        self
+    }
+
+    /// Use the given CSRF state instead of a random one
+    pub fn state(mut self, state: impl Into<String>) -> Self {
+        self.state = Some(state.into());
+        self
+    }
+
+    /// Use the given PKCE verifier instead of a random one
+    pub fn verifier(mut self, verifier: impl Into<String>) -> Self {
+        self.verifier = Some(verifier.into());
+        self
+    }
+
+    /// Build the authorization URL and the matching flow
+    ///
+    /// The query parameters always come in the same order, so URLs can be
+    /// compared across runs and versions: `response_type`, `client_id`,
+    /// `redirect_uri`, `scope`, `code_challenge`, `code_challenge_method`,
+    /// `state`, the configured `extra_authorization_params` (provider
+    /// parameters first), then `nonce`, `prompt`, `login_hint` and the
+    /// `param` values in the order they were added. Query parameters already
+    /// present in the configured authorization URL come first.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` if a provided verifier isn't valid per RFC 7636,
+    /// or `UrlParse` if the configured authorization URL is invalid
+    pub fn build(self) -> Result<OAuthFlow> {
+        let config = self.config;
+        let request = self.request()?;
+        let mut url = Url::parse(&config.auth_url)?;
+        url.query_pairs_mut().extend_pairs(&request.params);
+        Ok(request.into_flow(url.to_string()))
+    }
+
+    /// Generate the flow's secrets and collect its authorization parameters
+    ///
+    /// `build` puts the parameters in the authorization URL, while a pushed
+    /// authorization request (RFC 9126) POSTs them instead.
+    pub(crate) fn request(self) -> Result<AuthorizationRequest> {
+        let (pkce_challenge, pkce_verifier) = match self.verifier {
+            Some(verifier) => {
+                validate_verifier(&verifier)?;
+                (pkce_challenge(&verifier), verifier)
+            }
+            None => generate_pkce_pair(self.config)?,
+        };
+        let state = match self.state {
+            Some(state) => state,
+            None => generate_random_state(self.config)?,
+        };
+        let nonce = if self.config.oidc_nonce {
+            Some(generate_random_state(self.config)?)
+        } else {
+            None
+        };
+        let scope = self
+            .scopes
+            .as_ref()
+            .unwrap_or(&self.config.scopes)
+            .join(" ");
+
+        let mut params: Vec<(String, String)> =
+            authorization_params(self.config, &scope, &pkce_challenge, &state)
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+        if let Some(nonce) = &nonce {
+            params.push(("nonce".to_string(), nonce.clone()));
+        }
+        if let Some(prompt) = self.prompt {
+            params.push(("prompt".to_string(), prompt));
+        }
+        if let Some(login_hint) = self.login_hint {
+            params.push(("login_hint".to_string(), login_hint));
+        }
+        params.extend(self.extra_params);
+
+        Ok(AuthorizationRequest {
+            params,
+            pkce_verifier,
+            pkce_challenge,
+            state,
+            nonce,
+        })
+    }
+}
+
+/// Authorization parameters of a flow, before they're sent to the server
+pub(crate) struct AuthorizationRequest {
+    /// The parameters in the order documented on `AuthorizationUrlBuilder::build`
+    pub(crate) params: Vec<(String, String)>,
+    pkce_verifier: String,
+    pkce_challenge: String,
+    state: String,
+    nonce: Option<String>,
+}
+
+impl AuthorizationRequest {
+    /// Turn the request into a flow that sends the user to `authorization_url`
+    pub(crate) fn into_flow(self, authorization_url: String) -> OAuthFlow {
+        OAuthFlow {
+            authorization_url,
+            pkce_verifier: self.pkce_verifier,
+            pkce_challenge: self.pkce_challenge,
+            state: self.state,
+            nonce: self.nonce,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_overrides() {
+        let config = OAuthConfig::default();
+        let flow = AuthorizationUrlBuilder::new(&config)
+            .scopes(["openid", "email"])
+            .prompt("login")
+            .login_hint("user@example.com")
+            .param("audience", "api")
+            .state("fixed")
+            .build()
+            .unwrap();
+
+        let url = Url::parse(&flow.authorization_url).unwrap();
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        assert_eq!(param("scope").as_deref(), Some("openid email"));
+        assert_eq!(param("prompt").as_deref(), Some("login"));
+        assert_eq!(param("login_hint").as_deref(), Some("user@example.com"));
+        assert_eq!(param("audience").as_deref(), Some("api"));
+        assert_eq!(param("state").as_deref(), Some("fixed"));
+        assert_eq!(param("code_challenge"), Some(flow.pkce_challenge));
+    }
+
+    #[test]
+    fn test_parameter_order_is_stable() {
+        let config = OAuthConfig::builder()
+            .auth_url("https://auth.example.com/authorize?tenant=t1")
+            .authorization_param("resource", "api")
+            .oidc_nonce(true)
+            .build();
+        let flow = AuthorizationUrlBuilder::new(&config)
+            .param("z", "1")
+            .login_hint("user@example.com")
+            .prompt("login")
+            .param("a", "2")
+            .build()
+            .unwrap();
+
+        let url = Url::parse(&flow.authorization_url).unwrap();
+        let keys: Vec<String> = url.query_pairs().map(|(key, _)| key.into_owned()).collect();
+        assert_eq!(
+            keys,
+            [
+                "tenant",
+                "response_type",
+                "client_id",
+                "redirect_uri",
+                "scope",
+                "code_challenge",
+                "code_challenge_method",
+                "state",
+                "id_token_add_organizations",
+                "codex_cli_simplified_flow",
+                "originator",
+                "resource",
+                "nonce",
+                "prompt",
+                "login_hint",
+                "z",
+                "a",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_provider_params() {
+        let config = OAuthConfig::builder()
+            .provider(crate::Provider::Custom(crate::ProviderPreset {
+                client_id: "my-client".to_string(),
+                auth_url: "https://idp.example.com/authorize".to_string(),
+                token_url: "https://idp.example.com/token".to_string(),
+                scopes: vec!["openid".to_string()],
+                authorization_params: vec![("audience".to_string(), "api".to_string())],
+            }))
+            .authorization_param("resource", "https://api.example.com")
+            .build();
+        let flow = AuthorizationUrlBuilder::new(&config).build().unwrap();
+
+        let url = Url::parse(&flow.authorization_url).unwrap();
+        assert_eq!(url.host_str(), Some("idp.example.com"));
+        let params: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(param("client_id"), Some("my-client"));
+        assert_eq!(param("scope"), Some("openid"));
+        assert_eq!(param("audience"), Some("api"));
+        assert_eq!(param("resource"), Some("https://api.example.com"));
+        assert_eq!(param("originator"), None);
+
+        let openai = AuthorizationUrlBuilder::new(&OAuthConfig::default())
+            .build()
+            .unwrap();
+        assert!(openai.authorization_url.contains("originator=codex_cli_rs"));
+        assert!(
+            openai
+                .authorization_url
+                .contains("scope=openid+profile+email+offline_access")
+        );
+    }
+}
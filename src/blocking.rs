@@ -1,13 +1,35 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
 use url::Url;
 
-use crate::types::TokenResponse;
-use crate::{OAuthConfig, OAuthFlow, OpenAIAuthError, Result, TokenSet};
+use crate::api_key::{check_api_key_format, describe_error};
+use crate::device::{PollOutcome, PollSchedule, poll_error};
+use crate::error::{
+    api_key_exchange_error, blocking_response_error, exchange_error, http_error, refresh_error,
+};
+use crate::metrics::{ExchangeMeta, FlowTimings, NoopMetrics, Operation, record};
+use crate::observer::{NoopObserver, check_lifetime};
+use crate::types::{
+    MAX_PARALLEL_REFRESHES, ParResponse, TokenResponse, parse_json, token_exchange_params,
+    token_form,
+};
+use crate::{
+    ApiKeyInfo, AuthorizationUrlBuilder, Clock, DeviceAuthorization, FlowObserver, LazyApiKey,
+    Metrics, OAuthConfig, OAuthFlow, OpenAIAuthError, OrgInfo, Result, Session, StoreLock,
+    SystemClock, TokenSet, TokenStore,
+};
 
 /// Blocking OpenAI OAuth client for authentication
 ///
 /// This client handles the OAuth 2.0 flow with PKCE for OpenAI/ChatGPT authentication
 /// using blocking/synchronous operations.
 ///
+/// Cloning is cheap: clones share the configuration and the underlying HTTP
+/// client, and therefore its connection pool. Clones compare equal to each
+/// other until one of them is reconfigured.
+///
 /// # Example
 ///
 /// ```no_run
@@ -25,8 +47,13 @@ use crate::{OAuthConfig, OAuthFlow, OpenAIAuthError, Result, TokenSet};
 ///     Ok(())
 /// }
 /// ```
+#[derive(Clone)]
 pub struct OAuthClient {
-    config: OAuthConfig,
+    config: Arc<OAuthConfig>,
+    http: reqwest::blocking::Client,
+    observer: Arc<dyn FlowObserver>,
+    metrics: Arc<dyn Metrics>,
+    clock: Arc<dyn Clock>,
 }
 
 impl OAuthClient {
@@ -34,13 +61,85 @@ impl OAuthClient {
     ///
     /// # Arguments
     ///
-    /// * `config` - OAuth configuration (client ID, endpoints, redirect URI)
+    /// * `config` - OAuth configuration (client ID, endpoints, redirect URI, User-Agent)
     ///
     /// # Errors
     ///
-    /// Returns an error if the configuration is invalid
+    /// Returns an error if the configuration is invalid or the HTTP client can't be built
     pub fn new(config: OAuthConfig) -> Result<Self> {
-        Ok(Self { config })
+        let mut builder = reqwest::blocking::Client::builder();
+        if let Some(user_agent) = &config.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Some(timeout) = config.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = config.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        let tuning = &config.http_tuning;
+        builder = builder
+            .pool_idle_timeout(tuning.pool_idle_timeout)
+            .tcp_keepalive(tuning.tcp_keepalive);
+        if let Some(max_idle) = tuning.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+        if !config.cert_pins.is_empty() {
+            #[cfg(feature = "cert-pinning")]
+            {
+                builder =
+                    builder.use_preconfigured_tls(crate::pinning::pinned_tls_config(&config)?);
+            }
+            #[cfg(not(feature = "cert-pinning"))]
+            return Err(OpenAIAuthError::ClientCreation(
+                "certificate pinning requires the cert-pinning feature".to_string(),
+            ));
+        }
+        let http = builder
+            .build()
+            .map_err(|e| OpenAIAuthError::ClientCreation(e.to_string()))?;
+        Ok(Self {
+            config: Arc::new(config),
+            http,
+            observer: Arc::new(NoopObserver),
+            metrics: Arc::new(NoopMetrics),
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    /// Switch to a different OAuth client ID
+    ///
+    /// The new client ID is used for all subsequent requests, including
+    /// refreshes, which allows rotating client credentials without recreating
+    /// the client. Note that tokens issued under the previous client ID may
+    /// fail to refresh under the new one.
+    pub fn set_client_id(&mut self, client_id: impl Into<String>) {
+        Arc::make_mut(&mut self.config).client_id = client_id.into();
+    }
+
+    /// Register an observer that is notified at each step of the flow
+    ///
+    /// See `FlowObserver` for the available events.
+    pub fn with_observer(mut self, observer: impl FlowObserver + 'static) -> Self {
+        self.observer = Arc::new(observer);
+        self
+    }
+
+    /// Register a metrics hook for token endpoint outcomes and latencies
+    ///
+    /// See `Metrics` for the reported values.
+    pub fn with_metrics(mut self, metrics: impl Metrics + 'static) -> Self {
+        self.metrics = Arc::new(metrics);
+        self
+    }
+
+    /// Use a custom clock for expiry decisions
+    ///
+    /// The clock sets `TokenSet::expires_at` of received tokens and decides
+    /// when `refresh_and_store` refreshes. Defaults to `SystemClock`.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
     }
 
     /// Start the OAuth authorization flow
@@ -53,6 +152,11 @@ impl OAuthClient {
     /// An `OAuthFlow` containing the authorization URL, PKCE verifier,
     /// and CSRF state token
     ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` if `OAuthConfig::state_entropy_bytes` is below
+    /// the minimum of 16, or an error if the authorization URL is invalid
+    ///
     /// # Example
     ///
     /// ```no_run
@@ -66,28 +170,118 @@ impl OAuthClient {
     /// ```
     pub fn start_flow(&self) -> Result<OAuthFlow> {
         // Generate random state for CSRF protection
-        let state = crate::types::generate_random_state();
-        let (pkce_challenge, pkce_verifier) = crate::types::generate_pkce_pair();
+        self.start_flow_with_state(crate::types::generate_random_state(&self.config)?)
+    }
+
+    /// Start `n` independent OAuth authorization flows
+    ///
+    /// Each flow gets its own PKCE verifier and a state that is unique
+    /// within the batch. Useful for onboarding many accounts at once.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if building an authorization URL fails
+    pub fn start_flows(&self, n: usize) -> Result<Vec<OAuthFlow>> {
+        let mut states = std::collections::HashSet::with_capacity(n);
+        let mut flows = Vec::with_capacity(n);
+        while flows.len() < n {
+            let state = crate::types::generate_random_state(&self.config)?;
+            if states.insert(state.clone()) {
+                flows.push(self.start_flow_with_state(state)?);
+            }
+        }
+        Ok(flows)
+    }
+
+    /// Start the OAuth authorization flow with a caller-provided CSRF state
+    ///
+    /// This is like `start_flow`, but uses the given state instead of a
+    /// random one. Useful when the state must be known before the flow is
+    /// started, e.g. to start `run_callback_server_dynamic` first. The state
+    /// should be unguessable.
+    pub fn start_flow_with_state(&self, state: impl Into<String>) -> Result<OAuthFlow> {
+        self.start_flow_from(self.authorization_url_builder().state(state))
+    }
+
+    /// Start the OAuth authorization flow with a caller-provided state and PKCE verifier
+    ///
+    /// The PKCE challenge is computed from the verifier, so the same inputs
+    /// always produce the same authorization URL. This is useful in tests and
+    /// when a second process needs to reconstruct the exact same flow. Use
+    /// `start_flow` in production so both values are random.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` if the verifier isn't valid per RFC 7636
+    pub fn start_flow_with(&self, state: &str, verifier: &str) -> Result<OAuthFlow> {
+        self.start_flow_from(
+            self.authorization_url_builder()
+                .state(state)
+                .verifier(verifier),
+        )
+    }
+
+    /// Start a silent re-authentication flow (`prompt=none`)
+    ///
+    /// The authorization server completes the flow without showing any UI if
+    /// the user already has a session and has granted consent. Otherwise the
+    /// callback fails with `OpenAIAuthError::InteractionRequired`, signaling
+    /// that the app should fall back to an interactive flow with `start_flow`.
+    pub fn start_flow_silent(&self) -> Result<OAuthFlow> {
+        self.start_flow_from(self.authorization_url_builder().prompt("none"))
+    }
+
+    /// Create an `AuthorizationUrlBuilder` for this client's configuration
+    ///
+    /// Pass the customized builder to `start_flow_from`.
+    pub fn authorization_url_builder(&self) -> AuthorizationUrlBuilder<'_> {
+        AuthorizationUrlBuilder::new(&self.config)
+    }
+
+    /// Start the OAuth authorization flow from a customized builder
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the builder fails (see `AuthorizationUrlBuilder::build`)
+    pub fn start_flow_from(&self, builder: AuthorizationUrlBuilder<'_>) -> Result<OAuthFlow> {
+        let flow = builder.build()?;
+        self.observer.on_flow_started(&flow);
+        Ok(flow)
+    }
+
+    /// Start the OAuth authorization flow using a pushed authorization request
+    ///
+    /// Implements RFC 9126: the authorization parameters (including the PKCE
+    /// challenge and state) are POSTed to the configured `par_endpoint`, and
+    /// the returned authorization URL only references the resulting
+    /// `request_uri`, keeping those parameters off the front channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` if no PAR endpoint is configured, or an error if
+    /// the pushed authorization request fails
+    pub fn start_flow_par(&self) -> Result<OAuthFlow> {
+        let par_endpoint = self.config.par_endpoint.as_deref().ok_or_else(|| {
+            OpenAIAuthError::InvalidConfig("no PAR endpoint configured".to_string())
+        })?;
+
+        let request = self.authorization_url_builder().request()?;
+        let response = self.form_request(par_endpoint, &request.params).send()?;
+
+        if !response.status().is_success() {
+            return Err(blocking_response_error(response, http_error));
+        }
+
+        let par: ParResponse = parse_json(&response.text()?, OpenAIAuthError::OAuth)?;
 
-        // Build authorization URL
         let mut url = Url::parse(&self.config.auth_url)?;
         url.query_pairs_mut()
-            .append_pair("response_type", "code")
             .append_pair("client_id", &self.config.client_id)
-            .append_pair("redirect_uri", &self.config.redirect_uri)
-            .append_pair("scope", "openid profile email offline_access")
-            .append_pair("code_challenge", &pkce_challenge)
-            .append_pair("code_challenge_method", "S256")
-            .append_pair("state", &state)
-            .append_pair("id_token_add_organizations", "true")
-            .append_pair("codex_cli_simplified_flow", "true")
-            .append_pair("originator", "codex_cli_rs");
-
-        Ok(OAuthFlow {
-            authorization_url: url.to_string(),
-            pkce_verifier,
-            state,
-        })
+            .append_pair("request_uri", &par.request_uri);
+
+        let flow = request.into_flow(url.to_string());
+        self.observer.on_flow_started(&flow);
+        Ok(flow)
     }
 
     /// Exchange an authorization code for access and refresh tokens
@@ -95,6 +289,10 @@ impl OAuthClient {
     /// After the user authorizes the application, they'll receive an authorization
     /// code. This method exchanges that code for access and refresh tokens.
     ///
+    /// The code is cleaned up before sending: surrounding whitespace, a leading
+    /// `?`/`#`, pasted query parameters and fragments are removed, and
+    /// percent-encoded codes are decoded.
+    ///
     /// # Arguments
     ///
     /// * `code` - The authorization code from the OAuth callback
@@ -106,7 +304,12 @@ impl OAuthClient {
     ///
     /// # Errors
     ///
-    /// Returns an error if the token exchange fails (invalid code, network error, etc.)
+    /// Returns `InvalidConfig` if the PKCE verifier isn't valid per RFC 7636,
+    /// `AuthorizationCodeExpired` if the code expired before it was exchanged,
+    /// `OAuth` if no refresh token was issued and `OAuthConfig::require_refresh_token`
+    /// is set, or an error if the token exchange fails (invalid code, network
+    /// error, etc.). Without `require_refresh_token`, a missing refresh token
+    /// is logged as a warning and `TokenSet::is_refreshable` returns `false`.
     ///
     /// # Example
     ///
@@ -122,30 +325,179 @@ impl OAuthClient {
     /// # }
     /// ```
     pub fn exchange_code(&self, code: &str, verifier: &str) -> Result<TokenSet> {
-        let client = reqwest::blocking::Client::new();
+        let start = Instant::now();
+        let result = self.send_exchange_code(code, verifier, &mut ExchangeMeta::default());
+        record(&*self.metrics, Operation::Exchange, start, &result);
+        result
+    }
+
+    /// Exchange an authorization code for tokens and report request timings
+    ///
+    /// This behaves like `exchange_code`, and additionally returns a
+    /// `FlowTimings` breakdown of the token request for debugging slow
+    /// exchanges.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error `exchange_code` can return
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use openai_auth::{OAuthConfig, blocking::OAuthClient};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let client = OAuthClient::new(OAuthConfig::default())?;
+    /// # let flow = client.start_flow()?;
+    /// let (tokens, timings) = client.exchange_code_timed("code", &flow.pkce_verifier)?;
+    /// println!("TTFB {:?}, total {:?}", timings.time_to_first_byte, timings.total);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn exchange_code_timed(
+        &self,
+        code: &str,
+        verifier: &str,
+    ) -> Result<(TokenSet, FlowTimings)> {
+        let start = Instant::now();
+        let mut meta = ExchangeMeta::default();
+        let result = self.send_exchange_code(code, verifier, &mut meta);
+        record(&*self.metrics, Operation::Exchange, start, &result);
+        result.map(|tokens| (tokens, meta.timings))
+    }
+
+    /// Exchange an authorization code for tokens and report response details
+    ///
+    /// This behaves like `exchange_code`, and additionally returns the HTTP
+    /// status, the `x-request-id` header and the elapsed time of the
+    /// successful exchange for monitoring. Failed exchanges report the same
+    /// details through their error.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error `exchange_code` can return
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use openai_auth::{blocking::OAuthClient, OAuthConfig};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let client = OAuthClient::new(OAuthConfig::default())?;
+    /// # let flow = client.start_flow()?;
+    /// let (tokens, meta) = client.exchange_code_with_meta("code", &flow.pkce_verifier)?;
+    /// println!("{} in {:?} (request {:?})", meta.status, meta.elapsed, meta.request_id);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn exchange_code_with_meta(
+        &self,
+        code: &str,
+        verifier: &str,
+    ) -> Result<(TokenSet, ExchangeMeta)> {
+        let start = Instant::now();
+        let mut meta = ExchangeMeta::default();
+        let result = self.send_exchange_code(code, verifier, &mut meta);
+        record(&*self.metrics, Operation::Exchange, start, &result);
+        meta.elapsed = start.elapsed();
+        result.map(|tokens| (tokens, meta))
+    }
 
+    fn send_exchange_code(
+        &self,
+        code: &str,
+        verifier: &str,
+        meta: &mut ExchangeMeta,
+    ) -> Result<TokenSet> {
+        crate::types::validate_verifier(verifier)?;
+        self.observer.on_code_received();
+
+        let code = crate::types::sanitize_code(code);
         let params = [
             ("grant_type", "authorization_code"),
             ("client_id", &self.config.client_id),
-            ("code", code),
+            ("code", &code),
             ("code_verifier", verifier),
             ("redirect_uri", &self.config.redirect_uri),
         ];
 
-        let response = client
-            .post(&self.config.token_url)
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .form(&params)
-            .send()?;
+        let start = Instant::now();
+        let response = self.token_request(&params).send()?;
+        meta.timings.headers_received(start);
+        meta.status = response.status().as_u16();
+        meta.request_id = crate::error::request_id(response.headers());
 
         if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let body = response.text().unwrap_or_default();
-            return Err(OpenAIAuthError::Http { status, body });
+            return Err(blocking_response_error(response, exchange_error));
         }
 
-        let token_response: TokenResponse = response.json()?;
-        Ok(TokenSet::from(token_response))
+        let body = response.text()?;
+        meta.timings.finished(start);
+        let token_response: TokenResponse = parse_json(&body, OpenAIAuthError::TokenExchange)?;
+        let tokens = TokenSet::from_response(token_response, self.clock.now());
+        if !tokens.is_refreshable() {
+            if self.config.require_refresh_token {
+                return Err(OpenAIAuthError::OAuth(
+                    "offline_access not granted; no refresh token issued".to_string(),
+                ));
+            }
+            tracing::warn!("offline_access not granted; no refresh token issued");
+        }
+        check_lifetime(&*self.observer, &tokens, self.config.expiry_buffer);
+        self.observer.on_tokens_exchanged(&tokens);
+        Ok(tokens)
+    }
+
+    /// Complete a flow from the full redirect URL
+    ///
+    /// Extracts the authorization code from `callback_url` with
+    /// `parse_callback_url`, validates its state against `flow.state`,
+    /// exchanges it using the flow's PKCE verifier, and checks the flow's
+    /// nonce, if any, with `OAuthFlow::verify_nonce`. Use this when the user
+    /// pastes the URL from the browser's address bar.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback_url` - The full redirect URL, including its query string
+    /// * `flow` - The flow that produced the authorization URL
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL can't be parsed, carries an OAuth error,
+    /// has a mismatched state or no code, if the token exchange fails, or
+    /// `NonceMismatch` if the id_token doesn't carry the flow's nonce
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use openai_auth::{OAuthConfig, blocking::OAuthClient};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = OAuthClient::new(OAuthConfig::default())?;
+    /// let flow = client.start_flow()?;
+    /// let pasted = "http://localhost:1455/auth/callback?code=abc&state=xyz";
+    /// let tokens = client.complete_flow(pasted, &flow)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn complete_flow(&self, callback_url: &str, flow: &OAuthFlow) -> Result<TokenSet> {
+        let code = crate::parse_callback_url(callback_url, &flow.state)?;
+        let tokens = self.exchange_code(&code, &flow.pkce_verifier)?;
+        flow.verify_nonce(&tokens)?;
+        Ok(tokens)
+    }
+
+    /// Open a connection to the token endpoint ahead of the exchange
+    ///
+    /// Sends a `HEAD` request to the token endpoint so the DNS lookup, TCP
+    /// connect and TLS handshake are done while the user is still authorizing.
+    /// The connection stays in the client's pool and is reused by the
+    /// following `exchange_code`, which then only pays for the server
+    /// round-trip. The response status is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Network` if the endpoint can't be reached
+    pub fn warm_connection(&self) -> Result<()> {
+        self.http.head(&self.config.token_url).send()?;
+        Ok(())
     }
 
     /// Exchange an authorization code and return a TokenSet with an API key.
@@ -154,52 +506,178 @@ impl OAuthClient {
     /// OpenAI API key using the token-exchange grant.
     pub fn exchange_code_for_api_key(&self, code: &str, verifier: &str) -> Result<TokenSet> {
         let mut tokens = self.exchange_code(code, verifier)?;
+        self.ensure_api_key(&mut tokens)?;
+        Ok(tokens)
+    }
+
+    /// Populate `tokens.api_key` by exchanging the id_token, unless it's already set
+    ///
+    /// This avoids a redundant exchange request when the API key has already
+    /// been obtained, and makes it easy to fetch it lazily.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tokens have no id_token or the exchange fails
+    pub fn ensure_api_key(&self, tokens: &mut TokenSet) -> Result<()> {
+        if tokens.has_api_key() {
+            return Ok(());
+        }
         let id_token = tokens.id_token.as_deref().ok_or_else(|| {
             OpenAIAuthError::TokenExchange("missing id_token for api key exchange".to_string())
         })?;
-        let api_key = self.obtain_api_key(id_token)?;
-        tokens.api_key = Some(api_key);
-        Ok(tokens)
+        tokens.api_key = Some(self.obtain_api_key(id_token)?);
+        Ok(())
+    }
+
+    /// Get an API key for `tokens`, exchanging the id_token only on first use
+    ///
+    /// The key is cached in `cache` per id_token, so repeated calls don't hit
+    /// the network, and refreshed tokens with a new id_token are exchanged
+    /// again automatically.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tokens have no id_token or the exchange fails
+    pub fn api_key_cached(&self, cache: &LazyApiKey, tokens: &TokenSet) -> Result<String> {
+        let id_token = tokens.id_token.as_deref().ok_or_else(|| {
+            OpenAIAuthError::TokenExchange("missing id_token for api key exchange".to_string())
+        })?;
+        if let Some(key) = cache.get_for(id_token) {
+            return Ok(key);
+        }
+        let key = self.obtain_api_key(id_token)?;
+        cache.store(id_token, &key);
+        Ok(key)
     }
 
     /// Exchange an OpenAI id_token for an API key access token.
     pub fn obtain_api_key(&self, id_token: &str) -> Result<String> {
+        let start = Instant::now();
+        let result = self.send_token_exchange(id_token, None);
+        record(&*self.metrics, Operation::ApiKeyExchange, start, &result);
+        result
+    }
+
+    /// Exchange a user's id_token for an API key on behalf of another party
+    ///
+    /// Performs the same token exchange as `obtain_api_key`, adding the
+    /// RFC 8693 `actor_token` and `actor_token_type` parameters so a
+    /// service can assert its own identity while acting for the user, e.g.
+    /// in a multi-tenant backend. The authorization server decides whether
+    /// the actor may act for the subject.
+    ///
+    /// # Arguments
+    ///
+    /// * `subject_token` - The user's id_token
+    /// * `actor_token` - A token identifying the acting party
+    /// * `actor_token_type` - The token type URN of `actor_token`
+    ///   (e.g. `urn:ietf:params:oauth:token-type:access_token`)
+    ///
+    /// # Errors
+    ///
+    /// Returns `ApiKeyExchange` if the server rejects the exchange, or
+    /// another error for network failures
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use openai_auth::{OAuthConfig, blocking::OAuthClient};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let client = OAuthClient::new(OAuthConfig::default())?;
+    /// # let user_id_token = "";
+    /// let service_token = std::env::var("SERVICE_TOKEN")?;
+    /// let api_key = client.exchange_token(
+    ///     user_id_token,
+    ///     &service_token,
+    ///     "urn:ietf:params:oauth:token-type:access_token",
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn exchange_token(
+        &self,
+        subject_token: &str,
+        actor_token: &str,
+        actor_token_type: &str,
+    ) -> Result<String> {
+        let start = Instant::now();
+        let result = self.send_token_exchange(subject_token, Some((actor_token, actor_token_type)));
+        record(&*self.metrics, Operation::ApiKeyExchange, start, &result);
+        result
+    }
+
+    fn send_token_exchange(
+        &self,
+        subject_token: &str,
+        actor: Option<(&str, &str)>,
+    ) -> Result<String> {
         #[derive(serde::Deserialize)]
         struct ExchangeResponse {
             access_token: String,
         }
 
-        let client = reqwest::blocking::Client::new();
-        let params = [
-            (
-                "grant_type",
-                "urn:ietf:params:oauth:grant-type:token-exchange",
-            ),
-            ("client_id", &self.config.client_id),
-            ("requested_token", "openai-api-key"),
-            ("subject_token", id_token),
-            (
-                "subject_token_type",
-                "urn:ietf:params:oauth:token-type:id_token",
-            ),
-        ];
+        let params = token_exchange_params(&self.config, subject_token, actor);
 
-        let response = client
-            .post(&self.config.token_url)
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .form(&params)
-            .send()?;
+        let response = self.token_request(&params).send()?;
 
         if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let body = response.text().unwrap_or_default();
-            return Err(OpenAIAuthError::Http { status, body });
+            return Err(blocking_response_error(response, api_key_exchange_error));
         }
 
-        let exchange: ExchangeResponse = response.json()?;
+        let status = response.status().as_u16();
+        let request_id = crate::error::request_id(response.headers());
+
+        let exchange: ExchangeResponse =
+            parse_json(&response.text()?, |body| OpenAIAuthError::ApiKeyExchange {
+                status,
+                error_code: None,
+                body,
+                request_id,
+            })?;
         Ok(exchange.access_token)
     }
 
+    /// Look up the account an OpenAI API key belongs to
+    ///
+    /// Sends the key to the configured `api_key_info_url` and returns the
+    /// user and organizations it's associated with, e.g. to confirm which
+    /// account a pasted key is for. This is the inverse of
+    /// `obtain_api_key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidApiKey` if the string isn't an `sk-` key (without
+    /// sending it) or the API rejects it, and `Http` for other failures
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use openai_auth::{OAuthConfig, blocking::OAuthClient};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = OAuthClient::new(OAuthConfig::default())?;
+    /// let info = client.describe_api_key("sk-proj-...")?;
+    /// println!("Key belongs to {:?}", info.email);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn describe_api_key(&self, api_key: &str) -> Result<ApiKeyInfo> {
+        check_api_key_format(api_key)?;
+        let response = self
+            .http
+            .get(&self.config.api_key_info_url)
+            .bearer_auth(api_key.trim())
+            .send()?;
+
+        if !response.status().is_success() {
+            return Err(blocking_response_error(response, describe_error));
+        }
+
+        let status = response.status().as_u16();
+        let request_id = crate::error::request_id(response.headers());
+        let body = response.text()?;
+        ApiKeyInfo::from_body(status, &body, request_id)
+    }
+
     /// Refresh an expired access token
     ///
     /// When an access token expires, use the refresh token to obtain a new
@@ -215,7 +693,8 @@ impl OAuthClient {
     ///
     /// # Errors
     ///
-    /// Returns an error if the refresh fails (invalid refresh token, network error, etc.)
+    /// Returns `RefreshFailed` if the server rejects the refresh (e.g. an invalid
+    /// refresh token), or another error for network failures
     ///
     /// # Example
     ///
@@ -223,11 +702,7 @@ impl OAuthClient {
     /// # use openai_auth::{blocking::OAuthClient, OAuthConfig, TokenSet};
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// # let client = OAuthClient::new(OAuthConfig::default())?;
-    /// # let tokens = TokenSet {
-    /// #     access_token: "".into(),
-    /// #     refresh_token: "refresh".into(),
-    /// #     expires_at: 0,
-    /// # };
+    /// # let tokens = TokenSet::builder("", 0).refresh_token("refresh").build();
     /// if tokens.is_expired() {
     ///     let new_tokens = client.refresh_token(&tokens.refresh_token)?;
     ///     println!("Refreshed! New token expires in: {:?}", new_tokens.expires_in());
@@ -236,28 +711,310 @@ impl OAuthClient {
     /// # }
     /// ```
     pub fn refresh_token(&self, refresh_token: &str) -> Result<TokenSet> {
-        let client = reqwest::blocking::Client::new();
+        let start = Instant::now();
+        let result = self.send_refresh_token(refresh_token);
+        record(&*self.metrics, Operation::Refresh, start, &result);
+        result
+    }
 
+    fn send_refresh_token(&self, refresh_token: &str) -> Result<TokenSet> {
         let params = [
             ("grant_type", "refresh_token"),
             ("refresh_token", refresh_token),
             ("client_id", &self.config.client_id),
         ];
 
-        let response = client
-            .post(&self.config.token_url)
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .form(&params)
-            .send()?;
+        let response = self.token_request(&params).send()?;
 
         if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let body = response.text().unwrap_or_default();
-            return Err(OpenAIAuthError::ApiKeyExchange { status, body });
+            return Err(blocking_response_error(response, refresh_error));
+        }
+
+        let token_response: TokenResponse =
+            parse_json(&response.text()?, OpenAIAuthError::TokenRefresh)?;
+        let tokens = TokenSet::from_response(token_response, self.clock.now());
+        check_lifetime(&*self.observer, &tokens, self.config.expiry_buffer);
+        self.observer.on_refresh(&tokens);
+        Ok(tokens)
+    }
+
+    /// Refresh several accounts in parallel
+    ///
+    /// Refreshes run on up to 8 threads at a time, sharing this client's
+    /// connection pool. The results are in the same order as
+    /// `refresh_tokens`, and one failing refresh doesn't affect the others.
+    pub fn refresh_many(&self, refresh_tokens: &[String]) -> Vec<Result<TokenSet>> {
+        let mut results = Vec::with_capacity(refresh_tokens.len());
+        for chunk in refresh_tokens.chunks(MAX_PARALLEL_REFRESHES) {
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = chunk
+                    .iter()
+                    .map(|token| scope.spawn(move || self.refresh_token(token)))
+                    .collect();
+                results.extend(handles.into_iter().map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                }));
+            });
+        }
+        results
+    }
+
+    /// Bootstrap a full `TokenSet` from a stored refresh token
+    ///
+    /// Use this for cold starts when only the refresh token was persisted.
+    /// It refreshes immediately and keeps the given refresh token if the
+    /// server doesn't rotate it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the refresh fails
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use openai_auth::{blocking::OAuthClient, OAuthConfig};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = OAuthClient::new(OAuthConfig::default())?;
+    /// let tokens = client.from_refresh_token("stored-refresh-token")?;
+    /// println!("Access token expires in: {:?}", tokens.expires_in());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_refresh_token(&self, refresh_token: &str) -> Result<TokenSet> {
+        let mut tokens = self.refresh_token(refresh_token)?;
+        if tokens.refresh_token.is_empty() {
+            tokens.refresh_token = refresh_token.to_string();
+        }
+        Ok(tokens)
+    }
+
+    /// Obtain fresh tokens for a non-interactive environment such as CI
+    ///
+    /// Bootstraps a `TokenSet` from a refresh token stored as a secret, without
+    /// any browser or callback server. Surrounding whitespace, such as the
+    /// trailing newline of a secret file, is ignored. Persist the returned
+    /// refresh token if the pipeline can update its secret, since the server
+    /// may rotate it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` if the refresh token is empty, or an error if
+    /// the refresh fails
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use openai_auth::{OAuthConfig, blocking::OAuthClient};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = OAuthClient::new(OAuthConfig::default())?;
+    /// let tokens = client.login_headless(&std::env::var("OPENAI_REFRESH_TOKEN")?)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn login_headless(&self, refresh_token: &str) -> Result<TokenSet> {
+        let refresh_token = refresh_token.trim();
+        if refresh_token.is_empty() {
+            return Err(OpenAIAuthError::InvalidConfig(
+                "no refresh token provided".to_string(),
+            ));
         }
+        self.from_refresh_token(refresh_token)
+    }
+
+    /// Like `login_headless`, and also exchange the id_token for an API key
+    ///
+    /// # Errors
+    ///
+    /// Returns any error `login_headless` or `ensure_api_key` can return
+    pub fn login_headless_with_api_key(&self, refresh_token: &str) -> Result<TokenSet> {
+        let mut tokens = self.login_headless(refresh_token)?;
+        self.ensure_api_key(&mut tokens)?;
+        Ok(tokens)
+    }
 
-        let token_response: TokenResponse = response.json()?;
-        Ok(TokenSet::from(token_response))
+    /// Check whether a refresh token still works
+    ///
+    /// Performs a refresh and reports whether it succeeded, e.g. for a health
+    /// check of stored credentials at startup. The refresh may rotate the
+    /// refresh token, invalidating the one passed in; use
+    /// `try_refresh_token` instead to get the new tokens and persist them.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the refresh succeeded, `false` if the server rejected the
+    /// refresh token (`invalid_grant`)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for any other failure (network error, server error, etc.)
+    pub fn check_refresh_token(&self, refresh_token: &str) -> Result<bool> {
+        Ok(self.try_refresh_token(refresh_token)?.is_some())
+    }
+
+    /// Refresh the tokens, returning `None` if the refresh token was rejected
+    ///
+    /// Like `from_refresh_token`, but a refresh token rejected with
+    /// `invalid_grant` yields `Ok(None)` instead of an error. Persist the
+    /// returned tokens, since the refresh token may have been rotated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for any failure other than a rejected refresh token
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use openai_auth::{blocking::OAuthClient, OAuthConfig};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = OAuthClient::new(OAuthConfig::default())?;
+    /// match client.try_refresh_token("stored-refresh-token")? {
+    ///     Some(tokens) => println!("Still signed in: {}", tokens),
+    ///     None => println!("Please sign in again"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_refresh_token(&self, refresh_token: &str) -> Result<Option<TokenSet>> {
+        match self.from_refresh_token(refresh_token) {
+            Ok(tokens) => Ok(Some(tokens)),
+            Err(e) if e.is_invalid_grant() => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Refresh the tokens held in a `TokenStore` if needed and persist the result
+    ///
+    /// The store is locked for the duration of the call so that several
+    /// processes sharing the same store (for example a shell and a background
+    /// daemon) never refresh the same tokens twice. Tokens are reloaded after
+    /// the lock is acquired, so if another process already refreshed them the
+    /// fresh tokens are returned without a network request.
+    ///
+    /// Tokens are refreshed once they expire within the configured
+    /// `OAuthConfig::expiry_buffer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The store holding the tokens
+    ///
+    /// # Returns
+    ///
+    /// The current, non-expired `TokenSet`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store is empty, can't be locked, read or written,
+    /// or if the refresh fails
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use openai_auth::{blocking::OAuthClient, OAuthConfig, FileTokenStore};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = OAuthClient::new(OAuthConfig::default())?;
+    /// let store = FileTokenStore::new("tokens.json");
+    /// let tokens = client.refresh_and_store(&store)?;
+    /// println!("Token expires in: {:?}", tokens.expires_in());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn refresh_and_store(&self, store: &impl TokenStore) -> Result<TokenSet> {
+        let _lock = store.lock()?;
+
+        let tokens = store
+            .load()?
+            .ok_or_else(|| OpenAIAuthError::Storage("no tokens stored".to_string()))?;
+        if !tokens.is_expired_at(self.clock.now(), self.config.expiry_buffer) {
+            return Ok(tokens);
+        }
+
+        let mut tokens = tokens;
+        tokens.merge(self.refresh_token(tokens.usable_refresh_token()?)?);
+        store.save(&tokens)?;
+        Ok(tokens)
+    }
+
+    /// Refresh `tokens` in place if they expire within the configured buffer
+    ///
+    /// Does nothing while the tokens are still valid for longer than
+    /// `OAuthConfig::expiry_buffer`. Otherwise they are refreshed and the
+    /// response is merged in with `TokenSet::merge`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OAuth("no refresh token available")` if the tokens need a
+    /// refresh but aren't refreshable (see `TokenSet::is_refreshable`), or an
+    /// error if the refresh fails
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use openai_auth::{blocking::OAuthClient, OAuthConfig, TokenSet};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let client = OAuthClient::new(OAuthConfig::default())?;
+    /// # let mut tokens = TokenSet::builder("access", 0).refresh_token("refresh").build();
+    /// client.ensure_fresh(&mut tokens)?;
+    /// println!("Access token expires in: {:?}", tokens.expires_in());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn ensure_fresh(&self, tokens: &mut TokenSet) -> Result<()> {
+        if !tokens.is_expired_at(self.clock.now(), self.config.expiry_buffer) {
+            return Ok(());
+        }
+        tokens.merge(self.refresh_token(tokens.usable_refresh_token()?)?);
+        Ok(())
+    }
+
+    /// Load a session saved with `Session::save`, refreshing it if needed
+    ///
+    /// The session file is locked (via a sibling `<path>.lock` file) and
+    /// re-read, like `refresh_and_store`. If its tokens expire within the
+    /// configured `OAuthConfig::expiry_buffer`, they are refreshed, the
+    /// cached account details are updated from the new tokens, and the
+    /// session is saved back before being returned.
+    ///
+    /// # Returns
+    ///
+    /// The current session, or `None` if `path` doesn't exist (log in first)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be locked, read or written, or if
+    /// the refresh fails
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use openai_auth::{blocking::OAuthClient, OAuthConfig};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = OAuthClient::new(OAuthConfig::default())?;
+    /// if let Some(session) = client.load_session("session.json")? {
+    ///     println!("Logged in as {:?}", session.email);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_session(&self, path: impl AsRef<Path>) -> Result<Option<Session>> {
+        let path = path.as_ref();
+        let _lock = StoreLock::acquire(crate::store::sibling(path, ".lock"))?;
+
+        let Some(session) = Session::load(path)? else {
+            return Ok(None);
+        };
+        if !session
+            .tokens
+            .is_expired_at(self.clock.now(), self.config.expiry_buffer)
+        {
+            return Ok(Some(session));
+        }
+
+        let fresh = self.refresh_token(session.tokens.usable_refresh_token()?)?;
+        let session = session.refreshed(fresh);
+        session.save(path)?;
+        Ok(Some(session))
     }
 
     /// Extract ChatGPT account ID from an access token
@@ -279,10 +1036,568 @@ impl OAuthClient {
     pub fn extract_account_id(&self, access_token: &str) -> Result<String> {
         crate::jwt::extract_account_id(access_token)
     }
+
+    /// Extract ChatGPT account ID from an access token, optionally rejecting expired tokens
+    ///
+    /// Like `extract_account_id`, but with `validate_exp` set the token's own
+    /// `exp` claim is checked, independently of `TokenSet::expires_at`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token is malformed, expired (when
+    /// `validate_exp` is set), or doesn't contain the account ID
+    pub fn extract_account_id_checked(
+        &self,
+        access_token: &str,
+        validate_exp: bool,
+    ) -> Result<String> {
+        crate::jwt::extract_account_id_checked(access_token, validate_exp)
+    }
+
+    /// Extract the organizations granted to a token
+    ///
+    /// Returns an empty list if the token carries no organizations claim.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JWT is malformed
+    pub fn extract_organizations(&self, token: &str) -> Result<Vec<OrgInfo>> {
+        crate::jwt::extract_organizations(token)
+    }
+
+    /// Extract the user's email address from a token's `email` claim
+    ///
+    /// Returns `None` if the token has no `email` claim.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JWT is malformed
+    pub fn extract_email(&self, token: &str) -> Result<Option<String>> {
+        crate::jwt::extract_email(token)
+    }
+
+    /// Extract the user's email address from a token set
+    ///
+    /// Tries the access token first and falls back to the id_token. Returns
+    /// `None` if neither token carries an email.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the access token is malformed and there is no id_token
+    pub fn extract_email_from_tokens(&self, tokens: &TokenSet) -> Result<Option<String>> {
+        crate::jwt::extract_email_from_tokens(tokens)
+    }
+
+    /// Extract ChatGPT account ID from a token set
+    ///
+    /// Tries the access token first and falls back to the id_token, since
+    /// some token shapes only carry the account ID in the id_token.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither token contains the account ID
+    pub fn extract_account_id_from_tokens(&self, tokens: &TokenSet) -> Result<String> {
+        crate::jwt::extract_account_id_from_tokens(tokens)
+    }
+
+    /// Start the device authorization flow (RFC 8628)
+    ///
+    /// Requests a device code and user code from the configured
+    /// `device_authorization_url`. Show the user code and verification URI to
+    /// the user, then call `poll_device_token` to wait for authorization.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` if no device authorization endpoint is configured,
+    /// or an error if the request fails
+    pub fn start_device_flow(&self) -> Result<DeviceAuthorization> {
+        let url = self
+            .config
+            .device_authorization_url
+            .as_deref()
+            .ok_or_else(|| {
+                OpenAIAuthError::InvalidConfig(
+                    "no device authorization endpoint configured".to_string(),
+                )
+            })?;
+
+        let scope = self.config.scopes.join(" ");
+        let params = [
+            ("client_id", self.config.client_id.as_str()),
+            ("scope", &scope),
+        ];
+        let response = self.form_request(url, &params).send()?;
+
+        if !response.status().is_success() {
+            return Err(blocking_response_error(response, http_error));
+        }
+
+        parse_json(&response.text()?, OpenAIAuthError::OAuth)
+    }
+
+    /// Poll the token endpoint until the user completes the device flow
+    ///
+    /// Waits at least the server-provided interval between polls (plus a
+    /// little random jitter), backs off when the server responds with
+    /// `slow_down`, and gives up once the device code expires.
+    ///
+    /// # Arguments
+    ///
+    /// * `authorization` - The device authorization from `start_device_flow`
+    ///
+    /// # Errors
+    ///
+    /// Returns `OAuth("device code expired")` if the user doesn't authorize in
+    /// time, `AccessDenied` if the user rejects the request, or an error if a
+    /// request fails
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use openai_auth::{blocking::OAuthClient, OAuthConfig};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = OAuthConfig::builder()
+    ///     .device_authorization_url("https://auth.example.com/oauth/device/code")
+    ///     .build();
+    /// let client = OAuthClient::new(config)?;
+    /// let authorization = client.start_device_flow()?;
+    /// println!("Enter {} at {}", authorization.user_code, authorization.verification_uri);
+    /// let tokens = client.poll_device_token(&authorization)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn poll_device_token(&self, authorization: &DeviceAuthorization) -> Result<TokenSet> {
+        let mut schedule = PollSchedule::new(authorization);
+
+        loop {
+            let delay = schedule.next_delay()?;
+            std::thread::sleep(delay);
+
+            let params = [
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                ("device_code", &authorization.device_code),
+                ("client_id", &self.config.client_id),
+            ];
+            let response = self.token_request(&params).send()?;
+
+            if response.status().is_success() {
+                let token_response: TokenResponse =
+                    parse_json(&response.text()?, OpenAIAuthError::TokenExchange)?;
+                let tokens = TokenSet::from_response(token_response, self.clock.now());
+                check_lifetime(&*self.observer, &tokens, self.config.expiry_buffer);
+                self.observer.on_tokens_exchanged(&tokens);
+                return Ok(tokens);
+            }
+
+            match blocking_response_error(response, poll_error)? {
+                PollOutcome::Pending => {}
+                PollOutcome::SlowDown => schedule.slow_down(),
+            }
+        }
+    }
+
+    /// A copy of this client sending token requests to `token_url`
+    ///
+    /// Overrides the configured `token_url` for the code exchange, refresh
+    /// and API key exchange of the returned client, e.g. to exchange against
+    /// a test harness while refreshing against production, or to target a
+    /// per-tenant endpoint. The copy shares this client's connection pool,
+    /// observer, metrics and clock, so it is cheap to create per call.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use openai_auth::{blocking::OAuthClient, OAuthConfig};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = OAuthClient::new(OAuthConfig::default())?;
+    /// let tokens = client
+    ///     .with_token_url("http://localhost:8080/oauth/token")
+    ///     .refresh_token("refresh-token")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_token_url(&self, token_url: impl Into<String>) -> OAuthClient {
+        OAuthClient {
+            config: Arc::new(OAuthConfig {
+                token_url: token_url.into(),
+                ..(*self.config).clone()
+            }),
+            http: self.http.clone(),
+            observer: self.observer.clone(),
+            metrics: self.metrics.clone(),
+            clock: self.clock.clone(),
+        }
+    }
+
+    /// Build a form POST to the token endpoint, including any configured extra
+    /// form parameters and headers
+    fn token_request(&self, params: &[(&str, &str)]) -> reqwest::blocking::RequestBuilder {
+        let form = token_form(params, &self.config.extra_token_params);
+        self.form_request(&self.config.token_url, &form)
+    }
+
+    /// Build a form POST to `url`, including any configured extra headers
+    fn form_request(
+        &self,
+        url: &str,
+        params: &(impl serde::Serialize + ?Sized),
+    ) -> reqwest::blocking::RequestBuilder {
+        let mut request = self
+            .http
+            .post(url)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .form(params);
+        for (name, value) in &self.config.extra_headers {
+            request = request.header(name, value);
+        }
+        request
+    }
 }
 
+/// Clients are equal if one is a clone of the other, i.e. they share the same
+/// configuration and hooks.
+impl PartialEq for OAuthClient {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.config, &other.config)
+            && Arc::ptr_eq(&self.observer, &other.observer)
+            && Arc::ptr_eq(&self.metrics, &other.metrics)
+            && Arc::ptr_eq(&self.clock, &other.clock)
+    }
+}
+
+impl Eq for OAuthClient {}
+
 impl Default for OAuthClient {
     fn default() -> Self {
         Self::new(OAuthConfig::default()).expect("Failed to create OAuth client with defaults")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{endpoint, rejecting_endpoint, token_endpoint};
+    use crate::{FileTokenStore, ManualClock};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_error_variant_per_operation() {
+        let config = OAuthConfig::builder()
+            .token_url(rejecting_endpoint(4))
+            .build();
+        let client = OAuthClient::new(config).unwrap();
+
+        let exchange = client.exchange_code("code", &"a".repeat(43));
+        assert!(matches!(
+            exchange,
+            Err(OpenAIAuthError::Http { status: 400, .. })
+        ));
+
+        let refresh = client.refresh_token("refresh");
+        assert!(matches!(
+            refresh,
+            Err(OpenAIAuthError::RefreshFailed { status: 400, .. })
+        ));
+
+        assert!(!client.check_refresh_token("refresh").unwrap());
+
+        let api_key = client.obtain_api_key("id-token");
+        assert!(matches!(
+            api_key,
+            Err(OpenAIAuthError::ApiKeyExchange { status: 400, .. })
+        ));
+    }
+
+    #[test]
+    fn test_complete_flow_validates_state_and_exchanges() {
+        let config = OAuthConfig::builder()
+            .token_url(token_endpoint(
+                1,
+                "200 OK",
+                r#"{"access_token":"access","refresh_token":"refresh","expires_in":3600}"#,
+            ))
+            .build();
+        let client = OAuthClient::new(config).unwrap();
+        let flow = client.start_flow_with_state("s1").unwrap();
+
+        let forged = client.complete_flow(
+            "http://localhost:1455/auth/callback?code=abc&state=s2",
+            &flow,
+        );
+        assert!(matches!(forged, Err(OpenAIAuthError::OAuth(_))));
+
+        let tokens = client
+            .complete_flow(
+                "http://localhost:1455/auth/callback?code=abc&state=s1",
+                &flow,
+            )
+            .unwrap();
+        assert_eq!(tokens.access_token, "access");
+    }
+
+    #[test]
+    fn test_start_flow_par_pushes_builder_params() {
+        let par_endpoint = endpoint(1, |body| {
+            let params: Vec<(String, String)> = url::form_urlencoded::parse(body.as_bytes())
+                .into_owned()
+                .collect();
+            let keys: Vec<&str> = params.iter().map(|(key, _)| key.as_str()).collect();
+            assert!(keys.contains(&"code_challenge"), "{}", body);
+            assert!(keys.contains(&"state"), "{}", body);
+            assert!(keys.contains(&"nonce"), "{}", body);
+            ("201 Created", r#"{"request_uri":"urn:par:1"}"#.to_string())
+        });
+        let config = OAuthConfig::builder()
+            .par_endpoint(par_endpoint)
+            .oidc_nonce(true)
+            .build();
+        let client = OAuthClient::new(config).unwrap();
+
+        let flow = client.start_flow_par().unwrap();
+        let url = Url::parse(&flow.authorization_url).unwrap();
+        let keys: Vec<String> = url.query_pairs().map(|(key, _)| key.into_owned()).collect();
+        assert_eq!(keys, ["client_id", "request_uri"]);
+        assert!(flow.nonce.is_some());
+    }
+
+    #[test]
+    fn test_refresh_many_runs_in_bounded_chunks() {
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let count = 2 * MAX_PARALLEL_REFRESHES + 3;
+
+        // Echo the refresh token as the access token, after a delay that lets requests overlap
+        let token_url = endpoint(count, {
+            let (active, peak) = (active.clone(), peak.clone());
+            move |request| {
+                let running = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(running, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(50));
+                active.fetch_sub(1, Ordering::SeqCst);
+
+                let refresh_token = url::form_urlencoded::parse(request.as_bytes())
+                    .find(|(key, _)| key == "refresh_token")
+                    .unwrap()
+                    .1;
+                let body = format!(
+                    r#"{{"access_token":"{}","refresh_token":"rotated","expires_in":3600}}"#,
+                    refresh_token
+                );
+                ("200 OK", body)
+            }
+        });
+        let client = OAuthClient::new(OAuthConfig::builder().token_url(token_url).build()).unwrap();
+
+        let tokens: Vec<String> = (0..count).map(|i| format!("token-{}", i)).collect();
+        let results = client.refresh_many(&tokens);
+        assert_eq!(results.len(), count);
+        for (token, result) in tokens.iter().zip(results) {
+            assert_eq!(&result.unwrap().access_token, token);
+        }
+
+        let peak = peak.load(Ordering::SeqCst);
+        assert!(peak > 1, "refreshes ran serially");
+        assert!(
+            peak <= MAX_PARALLEL_REFRESHES,
+            "{} concurrent refreshes",
+            peak
+        );
+        assert!(client.refresh_many(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_refresh_many_keeps_failures_separate() {
+        let config = OAuthConfig::builder()
+            .token_url(rejecting_endpoint(3))
+            .build();
+        let client = OAuthClient::new(config).unwrap();
+
+        let tokens = ["a".to_string(), "b".to_string(), "c".to_string()];
+        let results = client.refresh_many(&tokens);
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|result| matches!(
+            result,
+            Err(OpenAIAuthError::RefreshFailed { status: 400, .. })
+        )));
+    }
+
+    #[test]
+    fn test_refresh_and_store_deduplicates_across_stores() {
+        let dir = std::env::temp_dir().join(format!(
+            "openai-auth-blocking-refresh-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tokens.json");
+        let expired = TokenSet::builder("stale", 0)
+            .refresh_token("refresh")
+            .build();
+        FileTokenStore::new(&path).save(&expired).unwrap();
+
+        // The endpoint answers a single request; a second refresh would fail to connect
+        let token_url = token_endpoint(
+            1,
+            "200 OK",
+            r#"{"access_token":"fresh","refresh_token":"rotated","expires_in":3600}"#,
+        );
+
+        // Separate clients and stores stand in for separate processes
+        let workers: Vec<_> = (0..2)
+            .map(|_| {
+                let (path, token_url) = (path.clone(), token_url.clone());
+                std::thread::spawn(move || {
+                    let config = OAuthConfig::builder().token_url(token_url).build();
+                    let client = OAuthClient::new(config).unwrap();
+                    client.refresh_and_store(&FileTokenStore::new(path))
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            let tokens = worker.join().unwrap().unwrap();
+            assert_eq!(tokens.access_token, "fresh");
+            assert_eq!(tokens.refresh_token, "rotated");
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_clock_drives_refresh_and_store() {
+        let dir =
+            std::env::temp_dir().join(format!("openai-auth-blocking-clock-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = FileTokenStore::new(dir.join("tokens.json"));
+        let tokens = TokenSet::builder("current", 2000)
+            .refresh_token("refresh")
+            .build();
+        store.save(&tokens).unwrap();
+
+        let clock = Arc::new(ManualClock::new(
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1000),
+        ));
+        let config = OAuthConfig::builder()
+            .token_url(token_endpoint(
+                1,
+                "200 OK",
+                r#"{"access_token":"fresh","refresh_token":"rotated","expires_in":3600}"#,
+            ))
+            .build();
+        let client = OAuthClient::new(config).unwrap().with_clock(clock.clone());
+
+        // 1000s left is outside the default 5 minute buffer
+        let current = client.refresh_and_store(&store).unwrap();
+        assert_eq!(current.access_token, "current");
+
+        clock.advance(Duration::from_secs(800));
+        let fresh = client.refresh_and_store(&store).unwrap();
+        assert_eq!(fresh.access_token, "fresh");
+        assert_eq!(fresh.expires_at, 1800 + 3600);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_session_refreshes_and_saves() {
+        let dir = std::env::temp_dir().join(format!(
+            "openai-auth-blocking-session-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.json");
+
+        let clock = Arc::new(ManualClock::new(
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1000),
+        ));
+        let config = OAuthConfig::builder()
+            .token_url(token_endpoint(
+                1,
+                "200 OK",
+                r#"{"access_token":"fresh","refresh_token":"rotated","expires_in":3600}"#,
+            ))
+            .build();
+        let client = OAuthClient::new(config).unwrap().with_clock(clock);
+        assert!(client.load_session(&path).unwrap().is_none());
+
+        let session = Session {
+            tokens: TokenSet::builder("current", 1100)
+                .refresh_token("refresh")
+                .build(),
+            account_id: Some("acc_1".into()),
+            email: None,
+            plan_type: None,
+        };
+        session.save(&path).unwrap();
+
+        let loaded = client.load_session(&path).unwrap().unwrap();
+        assert_eq!(loaded.tokens.access_token, "fresh");
+        assert_eq!(loaded.tokens.refresh_token, "rotated");
+        assert_eq!(loaded.account_id.as_deref(), Some("acc_1"));
+
+        // Saved back, so the next load needs no refresh
+        let reloaded = client.load_session(&path).unwrap().unwrap();
+        assert_eq!(reloaded.tokens.access_token, "fresh");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_poll_device_token_waits_for_authorization() {
+        let polls = Arc::new(AtomicUsize::new(0));
+        let token_url = endpoint(2, {
+            let polls = polls.clone();
+            move |request| {
+                assert!(request.contains("device_code=device"));
+                if polls.fetch_add(1, Ordering::SeqCst) == 0 {
+                    (
+                        "400 Bad Request",
+                        r#"{"error":"authorization_pending"}"#.to_string(),
+                    )
+                } else {
+                    (
+                        "200 OK",
+                        r#"{"access_token":"access","refresh_token":"refresh","expires_in":3600}"#
+                            .to_string(),
+                    )
+                }
+            }
+        });
+        let client = OAuthClient::new(OAuthConfig::builder().token_url(token_url).build()).unwrap();
+
+        let authorization = DeviceAuthorization {
+            device_code: "device".to_string(),
+            user_code: "ABCD-EFGH".to_string(),
+            verification_uri: "https://auth.openai.com/activate".to_string(),
+            verification_uri_complete: None,
+            expires_in: 30,
+            interval: 0,
+        };
+        let tokens = client.poll_device_token(&authorization).unwrap();
+        assert_eq!(tokens.access_token, "access");
+        assert_eq!(polls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_start_device_flow_sends_configured_scopes() {
+        let url = endpoint(1, |request| {
+            let scope = url::form_urlencoded::parse(request.as_bytes())
+                .find(|(key, _)| key == "scope")
+                .map(|(_, scope)| scope.into_owned());
+            assert_eq!(scope.as_deref(), Some("openid offline_access"));
+            (
+                "200 OK",
+                r#"{"device_code":"device","user_code":"ABCD","verification_uri":"https://auth.openai.com/activate","expires_in":600}"#
+                    .to_string(),
+            )
+        });
+        let config = OAuthConfig::builder()
+            .device_authorization_url(url)
+            .scopes(["openid", "offline_access"])
+            .build();
+        let client = OAuthClient::new(config).unwrap();
+
+        let authorization = client.start_device_flow().unwrap();
+        assert_eq!(authorization.user_code, "ABCD");
+    }
+}
@@ -11,7 +11,9 @@ use crate::{OpenAIAuthError, Result};
 ///
 /// # Errors
 ///
-/// Returns an error if the browser cannot be launched
+/// Returns `BrowserUnavailable` if there is no browser to launch on this
+/// system, in which case retrying won't help and the URL should be shown to
+/// the user instead, or `BrowserLaunch` if launching it failed
 ///
 /// # Example
 ///
@@ -29,6 +31,74 @@ use crate::{OpenAIAuthError, Result};
 /// # }
 /// ```
 pub fn open_browser(url: &str) -> Result<()> {
-    webbrowser::open(url)
-        .map_err(|e| OpenAIAuthError::BrowserLaunch(format!("Failed to open browser: {}", e)))
+    webbrowser::open(url).map_err(browser_error)
+}
+
+/// Tell "no browser on this system" apart from a failed launch
+///
+/// webbrowser reports missing browsers, missing launchers such as
+/// `xdg-open`, and unsupported platforms as `NotFound`.
+fn browser_error(error: std::io::Error) -> OpenAIAuthError {
+    match error.kind() {
+        std::io::ErrorKind::NotFound | std::io::ErrorKind::Unsupported => {
+            OpenAIAuthError::BrowserUnavailable(error)
+        }
+        _ => OpenAIAuthError::BrowserLaunch(format!("Failed to open browser: {}", error)),
+    }
+}
+
+/// Open a URL in the user's default web browser without blocking the executor
+///
+/// Like `open_browser`, but runs the (potentially blocking) platform call on
+/// tokio's blocking thread pool, so it's safe to call from async code. It
+/// must be called from within a tokio runtime.
+///
+/// # Arguments
+///
+/// * `url` - The URL to open
+///
+/// # Errors
+///
+/// Fails like `open_browser`
+///
+/// # Example
+///
+/// ```no_run
+/// use openai_auth::{OAuthClient, OAuthConfig, open_browser_async};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = OAuthClient::new(OAuthConfig::default())?;
+/// let flow = client.start_flow()?;
+///
+/// open_browser_async(&flow.authorization_url).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "async")]
+pub async fn open_browser_async(url: &str) -> Result<()> {
+    let url = url.to_string();
+    match tokio::task::spawn_blocking(move || open_browser(&url)).await {
+        Ok(result) => result,
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Error, ErrorKind};
+
+    #[test]
+    fn test_missing_browser_is_unavailable() {
+        let error = browser_error(Error::new(ErrorKind::NotFound, "no valid xdg config found"));
+        assert!(matches!(
+            &error,
+            OpenAIAuthError::BrowserUnavailable(e) if e.to_string() == "no valid xdg config found"
+        ));
+        assert_eq!(std::io::Error::from(error).kind(), ErrorKind::NotFound);
+
+        let error = browser_error(Error::other("xdg-open failed"));
+        assert!(matches!(error, OpenAIAuthError::BrowserLaunch(_)));
+    }
 }
@@ -0,0 +1,288 @@
+use url::Url;
+
+use crate::{OpenAIAuthError, Result};
+
+/// Extract the authorization code from a full redirect URL
+///
+/// Use this when the redirect doesn't reach the local callback server, e.g.
+/// when the user copies the URL from the browser's address bar, or with a
+/// custom-scheme redirect URI (`myapp://auth/callback`) that the operating
+/// system routes back to your application. Any URL scheme is accepted.
+///
+/// # Arguments
+///
+/// * `url` - The full redirect URL, including its query string
+/// * `expected_state` - The CSRF state token to validate against
+///
+/// # Returns
+///
+/// The authorization code from the redirect
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The URL can't be parsed
+/// - The redirect carries an OAuth error
+/// - The state token doesn't match
+/// - The code is missing
+///
+/// # Example
+///
+/// ```
+/// use openai_auth::parse_callback_url;
+///
+/// let code = parse_callback_url("myapp://auth/callback?code=abc&state=xyz", "xyz")?;
+/// assert_eq!(code, "abc");
+/// # Ok::<(), openai_auth::OpenAIAuthError>(())
+/// ```
+pub fn parse_callback_url(url: &str, expected_state: &str) -> Result<String> {
+    let url = Url::parse(url.trim())?;
+    let query = CallbackQuery::parse(url.query().unwrap_or(""));
+
+    if let Some(error) = query.error {
+        return Err(authorization_error(error));
+    }
+
+    if query.state.as_deref() != Some(expected_state) {
+        return Err(OpenAIAuthError::OAuth(
+            "State mismatch - possible CSRF attack".to_string(),
+        ));
+    }
+
+    query.code.ok_or(OpenAIAuthError::InvalidAuthorizationCode)
+}
+
+/// Read an authorization code from the environment variable `var`
+///
+/// For scripted flows where the code is delivered out of band, e.g. a test
+/// script that sets `OPENAI_AUTH_CODE` before running the exchange. The value
+/// is cleaned up like `OAuthClient::exchange_code` does, so it may also hold
+/// the full redirect URL.
+///
+/// # Errors
+///
+/// Returns `InvalidConfig` if the variable isn't set or isn't valid Unicode,
+/// or `InvalidAuthorizationCode` if it is empty
+///
+/// # Example
+///
+/// ```no_run
+/// use openai_auth::code_from_env;
+///
+/// let code = code_from_env("OPENAI_AUTH_CODE")?;
+/// # Ok::<(), openai_auth::OpenAIAuthError>(())
+/// ```
+pub fn code_from_env(var: &str) -> Result<String> {
+    let value = std::env::var(var).map_err(|e| {
+        OpenAIAuthError::InvalidConfig(format!(
+            "cannot read authorization code from {}: {}",
+            var, e
+        ))
+    })?;
+    code_from_value(&value)
+}
+
+/// Sanitize a pasted or injected authorization code, rejecting empty ones
+pub(crate) fn code_from_value(value: &str) -> Result<String> {
+    let code = crate::types::sanitize_code(value);
+    if code.is_empty() {
+        return Err(OpenAIAuthError::InvalidAuthorizationCode);
+    }
+    Ok(code)
+}
+
+/// The OAuth parameters of a redirect's query string
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct CallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+impl CallbackQuery {
+    /// Parse a query string (without the leading `?`)
+    ///
+    /// Names and values are form-decoded (`%XX` escapes and `+`), with
+    /// invalid UTF-8 replaced. The first occurrence of each parameter wins,
+    /// so a second `state` can't override the first, and empty values count
+    /// as missing. Any input is accepted.
+    pub(crate) fn parse(query: &str) -> Self {
+        let mut parsed = CallbackQuery::default();
+        for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            let slot = match &*key {
+                "code" => &mut parsed.code,
+                "state" => &mut parsed.state,
+                "error" => &mut parsed.error,
+                _ => continue,
+            };
+            if slot.is_none() && !value.is_empty() {
+                *slot = Some(value.into_owned());
+            }
+        }
+        parsed
+    }
+}
+
+/// Map the `error` parameter of an authorization redirect to an error
+///
+/// `login_required`, `interaction_required`, `consent_required` and
+/// `account_selection_required` (returned for `prompt=none` flows) map to
+/// `InteractionRequired`, so the caller can fall back to an interactive flow.
+pub(crate) fn authorization_error(error: String) -> OpenAIAuthError {
+    match error.as_str() {
+        "login_required"
+        | "interaction_required"
+        | "consent_required"
+        | "account_selection_required" => OpenAIAuthError::InteractionRequired(error),
+        "access_denied" => OpenAIAuthError::AccessDenied,
+        _ => OpenAIAuthError::OAuth(format!("OAuth error: {}", error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_callback_url_custom_scheme() {
+        let code = parse_callback_url("myapp://auth/callback?code=ac_1%2F2&state=s1", "s1");
+        assert_eq!(code.unwrap(), "ac_1/2");
+
+        let mismatch = parse_callback_url("myapp://auth/callback?code=abc&state=other", "s1");
+        assert!(matches!(mismatch, Err(OpenAIAuthError::OAuth(_))));
+
+        let missing = parse_callback_url("http://localhost:1455/auth/callback?state=s1", "s1");
+        assert!(matches!(
+            missing,
+            Err(OpenAIAuthError::InvalidAuthorizationCode)
+        ));
+
+        let silent =
+            parse_callback_url("myapp://auth/callback?error=login_required&state=s1", "s1");
+        assert!(matches!(
+            silent,
+            Err(OpenAIAuthError::InteractionRequired(e)) if e == "login_required"
+        ));
+    }
+
+    #[test]
+    fn test_code_from_env() {
+        assert!(matches!(
+            code_from_env("OPENAI_AUTH_TEST_CODE_UNSET"),
+            Err(OpenAIAuthError::InvalidConfig(_))
+        ));
+        assert_eq!(code_from_value(" ac_1\n").unwrap(), "ac_1");
+        assert_eq!(
+            code_from_value("http://localhost:1455/auth/callback?code=ac_2&state=s").unwrap(),
+            "ac_2"
+        );
+        assert!(matches!(
+            code_from_value("  "),
+            Err(OpenAIAuthError::InvalidAuthorizationCode)
+        ));
+    }
+
+    #[test]
+    fn test_callback_query_edge_cases() {
+        let query = CallbackQuery::parse("code=a%2Bb+c&state=s1&state=s2&code=other");
+        assert_eq!(query.code.as_deref(), Some("a+b c"));
+        assert_eq!(query.state.as_deref(), Some("s1"));
+
+        let query = CallbackQuery::parse("code&state=&error=&&=x&code=%ZZ%");
+        assert_eq!(query.code.as_deref(), Some("%ZZ%"));
+        assert_eq!(query.state, None);
+        assert_eq!(query.error, None);
+
+        let query = CallbackQuery::parse("code=%FF%FE&state=%E2%9C%93");
+        assert_eq!(query.code.as_deref(), Some("\u{FFFD}\u{FFFD}"));
+        assert_eq!(query.state.as_deref(), Some("\u{2713}"));
+
+        assert_eq!(CallbackQuery::parse(""), CallbackQuery::default());
+    }
+
+    /// Characters and fragments that matter to URL and query parsing
+    const FRAGMENTS: &[&str] = &[
+        "code",
+        "state",
+        "error",
+        "s1",
+        "=",
+        "&",
+        "?",
+        "#",
+        "%",
+        "%2",
+        "%26",
+        "%3D",
+        "%FF",
+        "+",
+        " ",
+        ";",
+        "/",
+        ":",
+        "\0",
+        "\u{2713}",
+        "\u{FFFD}",
+        "a",
+        "http://x/cb",
+        "myapp:",
+    ];
+
+    /// Check the invariants of both parsers on arbitrary input
+    fn check_parsers(input: &str) {
+        let query = CallbackQuery::parse(input);
+        for value in [&query.code, &query.state, &query.error] {
+            assert_ne!(value.as_deref(), Some(""), "{:?}", input);
+        }
+
+        match parse_callback_url(input, "s1") {
+            Ok(code) => {
+                assert!(!code.is_empty(), "{:?}", input);
+                let url = Url::parse(input.trim()).unwrap();
+                let query = CallbackQuery::parse(url.query().unwrap_or(""));
+                assert_eq!(query.state.as_deref(), Some("s1"), "{:?}", input);
+                assert_eq!(query.error, None, "{:?}", input);
+            }
+            Err(
+                OpenAIAuthError::UrlParse(_)
+                | OpenAIAuthError::OAuth(_)
+                | OpenAIAuthError::AccessDenied
+                | OpenAIAuthError::InteractionRequired(_)
+                | OpenAIAuthError::InvalidAuthorizationCode,
+            ) => {}
+            Err(e) => panic!("unexpected error {:?} for {:?}", e, input),
+        }
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_parsers_accept_any_string(input in proptest::prelude::any::<String>()) {
+            check_parsers(&input);
+        }
+
+        /// Mostly malformed input built from the fragments parsers care about
+        #[test]
+        fn test_parsers_accept_url_fragments(
+            fragments in proptest::collection::vec(proptest::sample::select(FRAGMENTS), 0..16),
+        ) {
+            check_parsers(&fragments.concat());
+        }
+
+        /// Any code and state survive encoding into a redirect URL
+        #[test]
+        fn test_parse_callback_url_round_trips(code in ".+", state in ".+") {
+            let query = url::form_urlencoded::Serializer::new(String::new())
+                .append_pair("code", &code)
+                .append_pair("state", &state)
+                .finish();
+            let url = format!("myapp://auth/callback?{}", query);
+
+            proptest::prop_assert_eq!(parse_callback_url(&url, &state).unwrap(), code);
+            let forged = format!("{}x", state);
+            proptest::prop_assert!(matches!(
+                parse_callback_url(&url, &forged),
+                Err(OpenAIAuthError::OAuth(_))
+            ));
+        }
+    }
+}
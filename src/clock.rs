@@ -0,0 +1,79 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Source of the current time
+///
+/// The clients use a `Clock` for every time-dependent decision, such as
+/// computing `TokenSet::expires_at` and checking expiry against
+/// `OAuthConfig::expiry_buffer`. Inject a `ManualClock` with
+/// `OAuthClient::with_clock` to test expiry and refresh logic deterministically
+/// without sleeping.
+pub trait Clock: Send + Sync {
+    /// The current time
+    fn now(&self) -> SystemTime;
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
+
+/// Clock reading the system time
+///
+/// This is the default clock of the clients.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock that only moves when told to
+///
+/// Share it through an `Arc` to keep advancing it after handing it to a
+/// client with `with_clock`.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::time::{Duration, SystemTime};
+/// use openai_auth::{Clock, ManualClock};
+///
+/// let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+/// let shared: Arc<dyn Clock> = clock.clone();
+/// clock.advance(Duration::from_secs(3600));
+/// assert_eq!(shared.now(), SystemTime::UNIX_EPOCH + Duration::from_secs(3600));
+/// ```
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<SystemTime>,
+}
+
+impl ManualClock {
+    /// Create a clock frozen at `now`
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    /// Move the clock forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+
+    /// Set the clock to `now`
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock().unwrap() = now;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap()
+    }
+}
@@ -0,0 +1,188 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{OpenAIAuthError, Result, TokenSet};
+
+/// The `auth.json` written by the Codex CLI
+///
+/// Current versions nest the tokens under `tokens`; early versions stored
+/// them at the top level.
+#[derive(Deserialize)]
+struct CodexAuthFile {
+    #[serde(rename = "OPENAI_API_KEY", default)]
+    openai_api_key: Option<String>,
+    #[serde(default)]
+    tokens: Option<CodexTokens>,
+    #[serde(flatten)]
+    legacy: LegacyTokens,
+}
+
+#[derive(Deserialize)]
+struct CodexTokens {
+    access_token: String,
+    refresh_token: String,
+    #[serde(default)]
+    id_token: Option<CodexIdToken>,
+}
+
+#[derive(Deserialize)]
+struct LegacyTokens {
+    #[serde(default)]
+    access_token: Option<String>,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    id_token: Option<CodexIdToken>,
+}
+
+/// An id_token, either as the raw JWT or as parsed info with a `raw_jwt` field
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CodexIdToken {
+    Raw(String),
+    Parsed { raw_jwt: String },
+}
+
+impl CodexIdToken {
+    fn into_raw(self) -> String {
+        match self {
+            CodexIdToken::Raw(jwt) | CodexIdToken::Parsed { raw_jwt: jwt } => jwt,
+        }
+    }
+}
+
+impl TokenSet {
+    /// Load the tokens of an existing Codex CLI login
+    ///
+    /// Parses the `auth.json` the Codex CLI writes (by default
+    /// `~/.codex/auth.json`), so an app can reuse that login instead of
+    /// running its own flow. The expected shape is:
+    ///
+    /// ```json
+    /// {
+    ///   "OPENAI_API_KEY": "sk-...",
+    ///   "tokens": {
+    ///     "id_token": "eyJ...",
+    ///     "access_token": "eyJ...",
+    ///     "refresh_token": "rt_...",
+    ///     "account_id": "..."
+    ///   },
+    ///   "last_refresh": "2025-01-01T00:00:00Z"
+    /// }
+    /// ```
+    ///
+    /// Files from early Codex versions, which keep the tokens at the top
+    /// level, and an `id_token` stored as an object with a `raw_jwt` field
+    /// are accepted too. `OPENAI_API_KEY` becomes `api_key`, and `expires_at`
+    /// is read from the access token's `exp` claim (0 if it has none, so the
+    /// tokens are refreshed before use). The account ID isn't stored in the
+    /// `TokenSet`; read it back with `extract_account_id_from_tokens`.
+    ///
+    /// Codex refreshes these tokens itself, so save refreshed tokens to your
+    /// own store rather than writing them back.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Storage` if the file can't be read or holds no ChatGPT
+    /// tokens (e.g. a Codex login with an API key only), or `Serialization`
+    /// if it isn't valid JSON
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use openai_auth::TokenSet;
+    ///
+    /// let home = std::env::var("HOME").unwrap();
+    /// let tokens = TokenSet::from_codex_auth_file(format!("{}/.codex/auth.json", home))?;
+    /// # Ok::<(), openai_auth::OpenAIAuthError>(())
+    /// ```
+    pub fn from_codex_auth_file(path: impl AsRef<Path>) -> Result<TokenSet> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| {
+            OpenAIAuthError::Storage(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let file: CodexAuthFile = serde_json::from_slice(&bytes)?;
+
+        let (access_token, refresh_token, id_token) = match (file.tokens, file.legacy) {
+            (Some(tokens), _) => (tokens.access_token, tokens.refresh_token, tokens.id_token),
+            (
+                None,
+                LegacyTokens {
+                    access_token: Some(access_token),
+                    refresh_token: Some(refresh_token),
+                    id_token,
+                },
+            ) => (access_token, refresh_token, id_token),
+            _ => {
+                return Err(OpenAIAuthError::Storage(format!(
+                    "{} contains no ChatGPT tokens",
+                    path.display()
+                )));
+            }
+        };
+
+        let expires_at = crate::jwt::extract_exp(&access_token)
+            .ok()
+            .flatten()
+            .unwrap_or(0);
+
+        Ok(TokenSet {
+            access_token,
+            id_token: id_token.map(CodexIdToken::into_raw),
+            refresh_token,
+            expires_at,
+            issued_at: None,
+            refresh_expires_at: None,
+            api_key: file.openai_api_key.filter(|key| !key.is_empty()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::unsigned_jwt;
+
+    fn load(json: &str) -> Result<TokenSet> {
+        let dir = std::env::temp_dir().join(format!("openai-auth-codex-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{:x}.json", json.len()));
+        std::fs::write(&path, json).unwrap();
+        let result = TokenSet::from_codex_auth_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn test_from_codex_auth_file_versions() {
+        let access = unsigned_jwt(r#"{"exp":1700000000}"#);
+
+        let current = format!(
+            r#"{{"OPENAI_API_KEY":"sk-1","tokens":{{"id_token":"id","access_token":"{}","refresh_token":"rt","account_id":"acc"}},"last_refresh":"2025-01-01T00:00:00Z"}}"#,
+            access
+        );
+        let tokens = load(&current).unwrap();
+        assert_eq!(tokens.access_token, access);
+        assert_eq!(tokens.refresh_token, "rt");
+        assert_eq!(tokens.id_token.as_deref(), Some("id"));
+        assert_eq!(tokens.api_key.as_deref(), Some("sk-1"));
+        assert_eq!(tokens.expires_at, 1700000000);
+
+        let parsed_id = r#"{"OPENAI_API_KEY":null,"tokens":{"id_token":{"email":"a@b.c","raw_jwt":"id"},"access_token":"opaque","refresh_token":"rt"}}"#;
+        let tokens = load(parsed_id).unwrap();
+        assert_eq!(tokens.id_token.as_deref(), Some("id"));
+        assert_eq!(tokens.api_key, None);
+        assert_eq!(tokens.expires_at, 0);
+
+        let legacy = r#"{"access_token":"at","refresh_token":"rt","id_token":"id"}"#;
+        let tokens = load(legacy).unwrap();
+        assert_eq!(tokens.access_token, "at");
+        assert_eq!(tokens.id_token.as_deref(), Some("id"));
+
+        assert!(matches!(
+            load(r#"{"OPENAI_API_KEY":"sk-1"}"#),
+            Err(OpenAIAuthError::Storage(_))
+        ));
+    }
+}
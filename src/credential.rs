@@ -0,0 +1,81 @@
+use base64::{Engine as _, engine::general_purpose};
+
+/// The kind of credential a string appears to be
+///
+/// Returned by `classify_credential`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialKind {
+    /// A JWT, such as an OAuth access token or id_token
+    Jwt,
+    /// An OpenAI API key (`sk-...`)
+    ApiKey,
+    /// An opaque token, such as an OAuth refresh token
+    Opaque,
+    /// Not a recognizable credential (empty or containing whitespace)
+    Unknown,
+}
+
+/// Guess what kind of credential a string is from its format alone
+///
+/// This is pure string inspection and makes no network requests. It's meant
+/// for routing "paste your token or API key" inputs, not for validation:
+/// a string classified as `Jwt` is structurally a JWT, but its signature and
+/// expiry aren't checked. Surrounding whitespace is ignored.
+///
+/// # Example
+///
+/// ```
+/// use openai_auth::{CredentialKind, classify_credential};
+///
+/// assert_eq!(classify_credential("sk-proj-abc123"), CredentialKind::ApiKey);
+/// assert_eq!(classify_credential("rt_abc123"), CredentialKind::Opaque);
+/// ```
+pub fn classify_credential(credential: &str) -> CredentialKind {
+    let credential = credential.trim();
+    if credential.is_empty() || credential.contains(char::is_whitespace) {
+        CredentialKind::Unknown
+    } else if credential.starts_with("sk-") {
+        CredentialKind::ApiKey
+    } else if is_jwt(credential) {
+        CredentialKind::Jwt
+    } else {
+        CredentialKind::Opaque
+    }
+}
+
+/// Check for three base64url segments whose header is a JSON object with `alg`
+fn is_jwt(credential: &str) -> bool {
+    let mut segments = credential.split('.');
+    let (Some(header), Some(_), Some(_), None) = (
+        segments.next(),
+        segments.next(),
+        segments.next(),
+        segments.next(),
+    ) else {
+        return false;
+    };
+
+    general_purpose::URL_SAFE_NO_PAD
+        .decode(header)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<serde_json::Value>(&bytes).ok())
+        .is_some_and(|header| header.get("alg").is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_credential() {
+        let header = general_purpose::URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256","typ":"JWT"}"#);
+        let jwt = format!("{}.eyJzdWIiOiIxIn0.c2ln", header);
+
+        assert_eq!(classify_credential(&jwt), CredentialKind::Jwt);
+        assert_eq!(classify_credential(" sk-abc123\n"), CredentialKind::ApiKey);
+        assert_eq!(classify_credential("a.b.c"), CredentialKind::Opaque);
+        assert_eq!(classify_credential("rt_abc123"), CredentialKind::Opaque);
+        assert_eq!(classify_credential(""), CredentialKind::Unknown);
+        assert_eq!(classify_credential("not a token"), CredentialKind::Unknown);
+    }
+}
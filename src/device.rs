@@ -0,0 +1,164 @@
+use serde::Deserialize;
+#[cfg(any(feature = "async", feature = "blocking"))]
+use std::time::Duration;
+
+#[cfg(any(feature = "async", feature = "blocking"))]
+use crate::{OpenAIAuthError, Result};
+
+/// Device authorization response (RFC 8628)
+///
+/// Returned by `start_device_flow`. Show `user_code` and `verification_uri`
+/// to the user, then poll for tokens with `poll_device_token`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceAuthorization {
+    /// The device verification code used when polling for tokens
+    pub device_code: String,
+    /// The code the user enters at the verification URI
+    pub user_code: String,
+    /// The URL the user should visit to enter the code
+    pub verification_uri: String,
+    /// A verification URL that already includes the user code, if provided
+    #[serde(default)]
+    pub verification_uri_complete: Option<String>,
+    /// Lifetime of the device code in seconds
+    pub expires_in: u64,
+    /// Minimum polling interval in seconds (default: 5)
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+/// Outcome of a single device token poll
+#[cfg(any(feature = "async", feature = "blocking"))]
+pub(crate) enum PollOutcome {
+    /// The user hasn't completed authorization yet
+    Pending,
+    /// The server asked us to poll less frequently
+    SlowDown,
+}
+
+#[cfg(any(feature = "async", feature = "blocking"))]
+#[derive(Deserialize)]
+struct DeviceErrorResponse {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+/// Map an error response from the token endpoint during device polling
+#[cfg(any(feature = "async", feature = "blocking"))]
+pub(crate) fn poll_error(
+    status: u16,
+    body: String,
+    request_id: Option<String>,
+) -> Result<PollOutcome> {
+    let Ok(response) = serde_json::from_str::<DeviceErrorResponse>(&body) else {
+        return Err(crate::error::http_error(status, body, request_id));
+    };
+
+    match response.error.as_str() {
+        "authorization_pending" => Ok(PollOutcome::Pending),
+        "slow_down" => Ok(PollOutcome::SlowDown),
+        "access_denied" => Err(OpenAIAuthError::AccessDenied),
+        "expired_token" => Err(OpenAIAuthError::OAuth("device code expired".to_string())),
+        _ => Err(OpenAIAuthError::OAuth(match response.error_description {
+            Some(description) => format!("{}: {}", response.error, description),
+            None => response.error,
+        })),
+    }
+}
+
+/// Polling schedule for the device flow
+///
+/// Starts at the server-provided interval, doubles it (by at least the
+/// 5 seconds mandated by RFC 8628) on `slow_down`, adds up to 10% random
+/// jitter to every wait, and gives up once the device code has expired.
+#[cfg(any(feature = "async", feature = "blocking"))]
+pub(crate) struct PollSchedule {
+    interval: Duration,
+    remaining: Duration,
+}
+
+#[cfg(any(feature = "async", feature = "blocking"))]
+impl PollSchedule {
+    pub(crate) fn new(authorization: &DeviceAuthorization) -> Self {
+        Self {
+            interval: Duration::from_secs(authorization.interval.max(1)),
+            remaining: Duration::from_secs(authorization.expires_in),
+        }
+    }
+
+    pub(crate) fn slow_down(&mut self) {
+        self.interval = (self.interval * 2).max(self.interval + Duration::from_secs(5));
+    }
+
+    /// The next delay to wait before polling again
+    ///
+    /// Returns an error if the device code will have expired by then.
+    pub(crate) fn next_delay(&mut self) -> Result<Duration> {
+        use rand::Rng;
+
+        let jitter = self
+            .interval
+            .mul_f64(rand::thread_rng().gen_range(0.0..0.1));
+        let delay = self.interval + jitter;
+        if delay >= self.remaining {
+            return Err(OpenAIAuthError::OAuth("device code expired".to_string()));
+        }
+        self.remaining -= delay;
+        Ok(delay)
+    }
+}
+
+#[cfg(all(test, any(feature = "async", feature = "blocking")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_error_mapping() {
+        let body = |error: &str| format!(r#"{{"error":"{}"}}"#, error);
+
+        assert!(matches!(
+            poll_error(400, body("authorization_pending"), None),
+            Ok(PollOutcome::Pending)
+        ));
+        assert!(matches!(
+            poll_error(400, body("slow_down"), None),
+            Ok(PollOutcome::SlowDown)
+        ));
+        assert!(matches!(
+            poll_error(400, body("access_denied"), None),
+            Err(OpenAIAuthError::AccessDenied)
+        ));
+        assert!(matches!(
+            poll_error(502, "Bad Gateway".to_string(), Some("req_123".into())),
+            Err(OpenAIAuthError::Http { status: 502, request_id: Some(id), .. }) if id == "req_123"
+        ));
+    }
+
+    #[test]
+    fn test_poll_schedule_slow_down_and_expiry() {
+        let authorization = DeviceAuthorization {
+            device_code: "device".into(),
+            user_code: "ABCD-EFGH".into(),
+            verification_uri: "https://example.com/device".into(),
+            verification_uri_complete: None,
+            expires_in: 30,
+            interval: 5,
+        };
+        let mut schedule = PollSchedule::new(&authorization);
+
+        let delay = schedule.next_delay().unwrap();
+        assert!(delay >= Duration::from_secs(5) && delay < Duration::from_millis(5500));
+
+        schedule.slow_down();
+        let delay = schedule.next_delay().unwrap();
+        assert!(delay >= Duration::from_secs(10));
+
+        schedule.slow_down();
+        assert!(schedule.next_delay().is_err());
+    }
+}
@@ -9,6 +9,17 @@ pub enum OpenAIAuthError {
     #[error("Invalid authorization code")]
     InvalidAuthorizationCode,
 
+    #[error("Authorization code expired, please restart the flow")]
+    AuthorizationCodeExpired {
+        status: u16,
+        /// The `error` field of the error body, always `invalid_grant`
+        error_code: Option<OAuthErrorCode>,
+        /// The token endpoint's error response body
+        body: String,
+        /// The `x-request-id` response header, if present
+        request_id: Option<String>,
+    },
+
     #[error("Token exchange failed: {0}")]
     TokenExchange(String),
 
@@ -24,29 +35,82 @@ pub enum OpenAIAuthError {
     #[error("Missing claim in JWT: {0}")]
     MissingJwtClaim(String),
 
+    #[cfg(any(feature = "async", feature = "blocking"))]
+    #[error("Network error: {0}")]
+    Network(#[source] reqwest::Error),
+
+    /// A request sent by the `minimal-http` client failed before a response
+    /// was received
+    #[cfg(feature = "minimal-http")]
     #[error("Network error: {0}")]
-    Network(#[from] reqwest::Error),
+    Transport(#[source] ureq::Error),
 
     #[error("HTTP error: {status}: {body}")]
-    Http { status: u16, body: String },
+    Http {
+        status: u16,
+        /// The `error` field of an RFC 6749 error body, if present
+        error_code: Option<OAuthErrorCode>,
+        body: String,
+        /// The `x-request-id` response header, if present
+        request_id: Option<String>,
+    },
 
     #[error("API key exchange failed: {status}: {body}")]
-    ApiKeyExchange { status: u16, body: String },
+    ApiKeyExchange {
+        status: u16,
+        /// The `error` field of an RFC 6749 error body, if present
+        error_code: Option<OAuthErrorCode>,
+        body: String,
+        /// The `x-request-id` response header, if present
+        request_id: Option<String>,
+    },
+
+    #[error("Token refresh failed: {status}: {body}")]
+    RefreshFailed {
+        status: u16,
+        /// The `error` field of an RFC 6749 error body, if present
+        error_code: Option<OAuthErrorCode>,
+        body: String,
+        /// The `x-request-id` response header, if present
+        request_id: Option<String>,
+    },
 
     #[error("OAuth error: {0}")]
     OAuth(String),
 
+    #[error("Invalid API key: {0}")]
+    InvalidApiKey(String),
+
+    #[error("The id_token nonce doesn't match the flow's nonce")]
+    NonceMismatch,
+
+    #[error("Authorization was denied by the user")]
+    AccessDenied,
+
+    #[error("User interaction required: {0}")]
+    InteractionRequired(String),
+
     #[error("URL parse error: {0}")]
     UrlParse(#[from] url::ParseError),
 
-    #[cfg(feature = "callback-server")]
+    #[cfg(any(feature = "callback-server", feature = "stdin"))]
     #[error("Callback server error: {0}")]
     CallbackServer(String),
 
+    #[cfg(feature = "callback-server")]
+    #[error("Timed out waiting for OAuth callback")]
+    CallbackTimeout,
+
     #[cfg(feature = "browser")]
     #[error("Failed to open browser: {0}")]
     BrowserLaunch(String),
 
+    /// No browser can be launched on this system (e.g. in a container, over
+    /// SSH, or on an unsupported platform); show the URL to the user instead
+    #[cfg(feature = "browser")]
+    #[error("No web browser available: {0}")]
+    BrowserUnavailable(#[source] std::io::Error),
+
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
@@ -56,9 +120,373 @@ pub enum OpenAIAuthError {
     #[error("Invalid configuration: {0}")]
     InvalidConfig(String),
 
+    #[error("Operation was cancelled")]
+    Cancelled,
+
+    #[error("Token storage error: {0}")]
+    Storage(String),
+
     #[error("Base64 decode error: {0}")]
     Base64Decode(#[from] base64::DecodeError),
 }
 
+impl OpenAIAuthError {
+    /// OpenAI's request id for a failed HTTP request
+    ///
+    /// Include it when filing a support case to speed up diagnosis.
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            OpenAIAuthError::Http { request_id, .. }
+            | OpenAIAuthError::ApiKeyExchange { request_id, .. }
+            | OpenAIAuthError::RefreshFailed { request_id, .. }
+            | OpenAIAuthError::AuthorizationCodeExpired { request_id, .. } => request_id.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The OAuth error code of a failed token endpoint request, if present
+    pub fn error_code(&self) -> Option<&OAuthErrorCode> {
+        match self {
+            OpenAIAuthError::Http { error_code, .. }
+            | OpenAIAuthError::ApiKeyExchange { error_code, .. }
+            | OpenAIAuthError::RefreshFailed { error_code, .. }
+            | OpenAIAuthError::AuthorizationCodeExpired { error_code, .. } => error_code.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// Error code of an OAuth error response (RFC 6749 section 5.2)
+///
+/// Parsed from the `error` field of error bodies and carried by the `Http`,
+/// `ApiKeyExchange`, `RefreshFailed` and `AuthorizationCodeExpired` errors,
+/// so callers can match on well-known codes.
+///
+/// # Example
+///
+/// ```
+/// use openai_auth::OAuthErrorCode;
+///
+/// let code: OAuthErrorCode = "invalid_grant".parse().unwrap();
+/// assert_eq!(code, OAuthErrorCode::InvalidGrant);
+/// assert_eq!(code.to_string(), "invalid_grant");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OAuthErrorCode {
+    /// `invalid_request`
+    InvalidRequest,
+    /// `invalid_grant`
+    InvalidGrant,
+    /// `invalid_client`
+    InvalidClient,
+    /// `unauthorized_client`
+    UnauthorizedClient,
+    /// `unsupported_grant_type`
+    UnsupportedGrantType,
+    /// `invalid_scope`
+    InvalidScope,
+    /// `access_denied`
+    AccessDenied,
+    /// `server_error`
+    ServerError,
+    /// `temporarily_unavailable`
+    TemporarilyUnavailable,
+    /// Any other code
+    Other(String),
+}
+
+impl OAuthErrorCode {
+    /// The code as it appears in the `error` field
+    pub fn as_str(&self) -> &str {
+        match self {
+            OAuthErrorCode::InvalidRequest => "invalid_request",
+            OAuthErrorCode::InvalidGrant => "invalid_grant",
+            OAuthErrorCode::InvalidClient => "invalid_client",
+            OAuthErrorCode::UnauthorizedClient => "unauthorized_client",
+            OAuthErrorCode::UnsupportedGrantType => "unsupported_grant_type",
+            OAuthErrorCode::InvalidScope => "invalid_scope",
+            OAuthErrorCode::AccessDenied => "access_denied",
+            OAuthErrorCode::ServerError => "server_error",
+            OAuthErrorCode::TemporarilyUnavailable => "temporarily_unavailable",
+            OAuthErrorCode::Other(code) => code,
+        }
+    }
+}
+
+impl std::str::FromStr for OAuthErrorCode {
+    type Err = std::convert::Infallible;
+
+    fn from_str(code: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match code {
+            "invalid_request" => OAuthErrorCode::InvalidRequest,
+            "invalid_grant" => OAuthErrorCode::InvalidGrant,
+            "invalid_client" => OAuthErrorCode::InvalidClient,
+            "unauthorized_client" => OAuthErrorCode::UnauthorizedClient,
+            "unsupported_grant_type" => OAuthErrorCode::UnsupportedGrantType,
+            "invalid_scope" => OAuthErrorCode::InvalidScope,
+            "access_denied" => OAuthErrorCode::AccessDenied,
+            "server_error" => OAuthErrorCode::ServerError,
+            "temporarily_unavailable" => OAuthErrorCode::TemporarilyUnavailable,
+            other => OAuthErrorCode::Other(other.to_string()),
+        })
+    }
+}
+
+impl std::fmt::Display for OAuthErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parse the error code of an OAuth error body, if it is one
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+pub(crate) fn error_code(body: &str) -> Option<OAuthErrorCode> {
+    let response = serde_json::from_str::<ErrorResponse>(body).ok()?;
+    let Ok(code) = response.error.parse();
+    Some(code)
+}
+
+/// Read the `x-request-id` header from a response
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+pub(crate) fn request_id(headers: &http::HeaderMap) -> Option<String> {
+    headers
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Map a failed response to the generic `Http` error
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+pub(crate) fn http_error(status: u16, body: String, request_id: Option<String>) -> OpenAIAuthError {
+    OpenAIAuthError::Http {
+        status,
+        error_code: error_code(&body),
+        body,
+        request_id,
+    }
+}
+
+/// Map a failed refresh response to `RefreshFailed`
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+pub(crate) fn refresh_error(
+    status: u16,
+    body: String,
+    request_id: Option<String>,
+) -> OpenAIAuthError {
+    OpenAIAuthError::RefreshFailed {
+        status,
+        error_code: error_code(&body),
+        body,
+        request_id,
+    }
+}
+
+/// Map a failed API key token exchange response to `ApiKeyExchange`
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+pub(crate) fn api_key_exchange_error(
+    status: u16,
+    body: String,
+    request_id: Option<String>,
+) -> OpenAIAuthError {
+    OpenAIAuthError::ApiKeyExchange {
+        status,
+        error_code: error_code(&body),
+        body,
+        request_id,
+    }
+}
+
+/// Read the status, request id and body of a failed response and map them with `map`
+///
+/// The body is empty if it can't be read, so the status is still reported.
+#[cfg(feature = "async")]
+pub(crate) async fn response_error<T>(
+    response: reqwest::Response,
+    map: impl FnOnce(u16, String, Option<String>) -> T,
+) -> T {
+    let status = response.status().as_u16();
+    let request_id = request_id(response.headers());
+    let body = response.text().await.unwrap_or_default();
+    map(status, body, request_id)
+}
+
+/// Blocking version of `response_error`
+#[cfg(feature = "blocking")]
+pub(crate) fn blocking_response_error<T>(
+    response: reqwest::blocking::Response,
+    map: impl FnOnce(u16, String, Option<String>) -> T,
+) -> T {
+    let status = response.status().as_u16();
+    let request_id = request_id(response.headers());
+    let body = response.text().unwrap_or_default();
+    map(status, body, request_id)
+}
+
+/// `minimal-http` version of `response_error`
+#[cfg(feature = "minimal-http")]
+pub(crate) fn minimal_response_error<T>(
+    response: ureq::http::Response<ureq::Body>,
+    map: impl FnOnce(u16, String, Option<String>) -> T,
+) -> T {
+    let status = response.status().as_u16();
+    let request_id = request_id(response.headers());
+    let body = response.into_body().read_to_string().unwrap_or_default();
+    map(status, body, request_id)
+}
+
+/// OAuth error response body (RFC 6749 section 5.2)
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+#[derive(serde::Deserialize)]
+struct ErrorResponse {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+/// Map a failed authorization code exchange to an error
+///
+/// An `invalid_grant` error whose description mentions expiry becomes
+/// `AuthorizationCodeExpired`; anything else is reported as `Http`.
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+pub(crate) fn exchange_error(
+    status: u16,
+    body: String,
+    request_id: Option<String>,
+) -> OpenAIAuthError {
+    if let Ok(response) = serde_json::from_str::<ErrorResponse>(&body)
+        && response.error == "invalid_grant"
+        && response
+            .error_description
+            .is_some_and(|description| description.to_ascii_lowercase().contains("expired"))
+    {
+        return OpenAIAuthError::AuthorizationCodeExpired {
+            status,
+            error_code: Some(OAuthErrorCode::InvalidGrant),
+            body,
+            request_id,
+        };
+    }
+
+    http_error(status, body, request_id)
+}
+
+impl OpenAIAuthError {
+    /// Whether the server rejected the refresh token (`invalid_grant`)
+    #[cfg(any(feature = "async", feature = "blocking"))]
+    pub(crate) fn is_invalid_grant(&self) -> bool {
+        matches!(
+            self,
+            OpenAIAuthError::RefreshFailed {
+                error_code: Some(OAuthErrorCode::InvalidGrant),
+                ..
+            }
+        )
+    }
+}
+
+#[cfg(any(feature = "async", feature = "blocking"))]
+impl From<reqwest::Error> for OpenAIAuthError {
+    fn from(error: reqwest::Error) -> Self {
+        #[cfg(feature = "cert-pinning")]
+        if crate::pinning::is_pin_mismatch(&error) {
+            return OpenAIAuthError::OAuth("certificate pin mismatch".to_string());
+        }
+        OpenAIAuthError::Network(error)
+    }
+}
+
+#[cfg(feature = "minimal-http")]
+impl From<ureq::Error> for OpenAIAuthError {
+    fn from(error: ureq::Error) -> Self {
+        OpenAIAuthError::Transport(error)
+    }
+}
+
+impl From<OpenAIAuthError> for std::io::Error {
+    fn from(error: OpenAIAuthError) -> Self {
+        use std::io::ErrorKind;
+
+        let kind = match &error {
+            OpenAIAuthError::AccessDenied => ErrorKind::PermissionDenied,
+            OpenAIAuthError::InvalidConfig(_) => ErrorKind::InvalidInput,
+            OpenAIAuthError::Cancelled => ErrorKind::Interrupted,
+            #[cfg(feature = "browser")]
+            OpenAIAuthError::BrowserUnavailable(_) => ErrorKind::NotFound,
+            #[cfg(feature = "callback-server")]
+            OpenAIAuthError::CallbackTimeout => ErrorKind::TimedOut,
+            _ => ErrorKind::Other,
+        };
+        std::io::Error::new(kind, error)
+    }
+}
+
 /// Result type alias for OpenAI authentication operations
 pub type Result<T> = std::result::Result<T, OpenAIAuthError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync_static<T: Send + Sync + 'static>() {}
+
+    #[test]
+    fn test_error_is_send_sync_static() {
+        assert_send_sync_static::<OpenAIAuthError>();
+    }
+
+    #[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+    #[test]
+    fn test_exchange_error_detects_expired_code() {
+        let body =
+            r#"{"error":"invalid_grant","error_description":"Authorization code has expired."}"#;
+        let err = exchange_error(400, body.to_string(), Some("req_123".to_string()));
+        assert_eq!(err.request_id(), Some("req_123"));
+        assert_eq!(err.error_code(), Some(&OAuthErrorCode::InvalidGrant));
+        assert!(matches!(
+            err,
+            OpenAIAuthError::AuthorizationCodeExpired { status: 400, body: ref b, .. } if b == body
+        ));
+
+        let body = r#"{"error":"invalid_grant","error_description":"Invalid authorization code"}"#;
+        assert!(matches!(
+            exchange_error(400, body.to_string(), None),
+            OpenAIAuthError::Http { status: 400, .. }
+        ));
+    }
+
+    #[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+    #[test]
+    fn test_oauth_error_code_round_trip() {
+        for code in [
+            "invalid_request",
+            "invalid_grant",
+            "invalid_client",
+            "unauthorized_client",
+            "unsupported_grant_type",
+            "invalid_scope",
+            "access_denied",
+            "server_error",
+            "temporarily_unavailable",
+            "custom_error",
+        ] {
+            let parsed: OAuthErrorCode = code.parse().unwrap();
+            assert_eq!(parsed.to_string(), code);
+        }
+        assert_eq!(
+            "custom_error".parse::<OAuthErrorCode>().unwrap(),
+            OAuthErrorCode::Other("custom_error".to_string())
+        );
+
+        let error = exchange_error(400, r#"{"error":"invalid_client"}"#.to_string(), None);
+        assert_eq!(error.error_code(), Some(&OAuthErrorCode::InvalidClient));
+        let error = exchange_error(502, "Bad Gateway".to_string(), None);
+        assert_eq!(error.error_code(), None);
+    }
+
+    #[test]
+    fn test_into_io_error() {
+        let error: std::io::Error = OpenAIAuthError::AccessDenied.into();
+        assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied);
+        assert!(error.get_ref().unwrap().is::<OpenAIAuthError>());
+    }
+}
@@ -1,13 +1,30 @@
-use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode};
+use jsonwebtoken::{DecodingKey, Validation, decode};
 use serde::{Deserialize, Serialize};
 
-use crate::{OpenAIAuthError, Result};
+use crate::{OpenAIAuthError, Result, TokenSet};
 
 /// OpenAI-specific auth claims within JWT
 #[derive(Debug, Serialize, Deserialize)]
 struct OpenAIAuth {
     #[serde(rename = "chatgpt_account_id")]
     chatgpt_account_id: Option<String>,
+    #[serde(default)]
+    chatgpt_plan_type: Option<String>,
+    #[serde(default)]
+    organizations: Vec<OrgInfo>,
+}
+
+/// An OpenAI organization granted to the token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrgInfo {
+    /// Organization ID
+    pub id: String,
+    /// Human-readable organization name
+    #[serde(default)]
+    pub title: Option<String>,
+    /// The user's role in the organization
+    #[serde(default)]
+    pub role: Option<String>,
 }
 
 /// JWT claims structure
@@ -15,6 +32,12 @@ struct OpenAIAuth {
 struct Claims {
     #[serde(rename = "https://api.openai.com/auth")]
     openai_auth: Option<OpenAIAuth>,
+    #[serde(default)]
+    email: Option<String>,
+    #[serde(default)]
+    nonce: Option<String>,
+    #[serde(default)]
+    exp: Option<u64>,
 }
 
 /// Extract ChatGPT account ID from access token JWT
@@ -37,24 +60,310 @@ struct Claims {
 /// - The JWT is malformed
 /// - The required claim is missing
 pub fn extract_account_id(token: &str) -> Result<String> {
-    // Decode without verification (we just need claims)
-    // The token comes from OpenAI's OAuth flow, so we trust it
-    let mut validation = Validation::new(Algorithm::RS256);
-    validation.insecure_disable_signature_validation();
-    validation.validate_exp = false;
-
-    let token_data = decode::<Claims>(token, &DecodingKey::from_secret(&[]), &validation)?;
+    extract_account_id_checked(token, false)
+}
 
-    token_data
-        .claims
+/// Extract ChatGPT account ID from a JWT, optionally rejecting expired tokens
+///
+/// Like `extract_account_id`, but with `validate_exp` set the token's own
+/// `exp` claim is checked (with 60 seconds of leeway). This is a lightweight
+/// local expiry check that doesn't rely on `TokenSet::expires_at`. The
+/// signature is still not verified.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The JWT is malformed
+/// - `validate_exp` is set and the token has expired
+/// - The required claim is missing
+pub fn extract_account_id_checked(token: &str, validate_exp: bool) -> Result<String> {
+    decode_claims(token, validate_exp)?
         .openai_auth
         .and_then(|auth| auth.chatgpt_account_id)
         .ok_or_else(|| OpenAIAuthError::MissingJwtClaim("chatgpt_account_id".to_string()))
 }
 
+/// Extract the user's email address from the standard `email` claim
+///
+/// # Returns
+///
+/// The email address, or `None` if the token has no `email` claim
+///
+/// # Errors
+///
+/// Returns an error if the JWT is malformed
+pub fn extract_email(token: &str) -> Result<Option<String>> {
+    Ok(decode_claims(token, false)?.email)
+}
+
+/// Extract the user's email address from a token set
+///
+/// Tries the access token first and falls back to the id_token, which
+/// carries the email when the `email` scope was granted.
+///
+/// # Errors
+///
+/// Returns the access token's error if it is malformed and there is no
+/// id_token
+pub fn extract_email_from_tokens(tokens: &TokenSet) -> Result<Option<String>> {
+    let from_access = extract_email(&tokens.access_token);
+    if let Ok(Some(email)) = from_access {
+        return Ok(Some(email));
+    }
+    match tokens.id_token.as_deref() {
+        Some(id_token) => extract_email(id_token),
+        None => from_access,
+    }
+}
+
+/// Read the ChatGPT plan type (e.g. "plus") from a token set
+///
+/// Tries the access token first and falls back to the id_token.
+pub(crate) fn extract_plan_type_from_tokens(tokens: &TokenSet) -> Option<String> {
+    std::iter::once(tokens.access_token.as_str())
+        .chain(tokens.id_token.as_deref())
+        .find_map(|token| {
+            decode_claims(token, false)
+                .ok()?
+                .openai_auth?
+                .chatgpt_plan_type
+        })
+}
+
+/// Read the `exp` claim of a JWT
+pub(crate) fn extract_exp(token: &str) -> Result<Option<u64>> {
+    Ok(decode_claims(token, false)?.exp)
+}
+
+/// Read the OIDC `nonce` claim of an id_token
+pub(crate) fn extract_nonce(token: &str) -> Result<Option<String>> {
+    Ok(decode_claims(token, false)?.nonce)
+}
+
+/// Extract the organizations granted to a token
+///
+/// The organizations are included when the flow requests
+/// `id_token_add_organizations=true` (as `start_flow` does). Returns an empty
+/// list if the claim is absent, since single-organization accounts may omit it.
+///
+/// # Errors
+///
+/// Returns an error if the JWT is malformed
+#[cfg(any(feature = "async", feature = "blocking"))]
+pub fn extract_organizations(token: &str) -> Result<Vec<OrgInfo>> {
+    Ok(decode_claims(token, false)?
+        .openai_auth
+        .map(|auth| auth.organizations)
+        .unwrap_or_default())
+}
+
+/// The header of a JWT
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct JwtHeader {
+    /// The signing algorithm (e.g. "RS256")
+    pub alg: String,
+    /// The token type (usually "JWT")
+    #[serde(default)]
+    pub typ: Option<String>,
+    /// The ID of the signing key, used to select a key from a JWKS
+    #[serde(default)]
+    pub kid: Option<String>,
+}
+
+/// Decode the header of a JWT without verifying the token
+///
+/// Useful for diagnostics, e.g. to check the algorithm of a token that
+/// fails to decode, or to find the `kid` of the key that signed it.
+/// Any algorithm name is accepted.
+///
+/// # Errors
+///
+/// Returns `InvalidJwt` if the token has no header or it isn't valid
+/// base64url-encoded JSON
+pub fn decode_jwt_header(token: &str) -> Result<JwtHeader> {
+    use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+
+    let header = token
+        .split('.')
+        .next()
+        .filter(|_| token.contains('.'))
+        .ok_or_else(|| OpenAIAuthError::InvalidJwt("missing JWT header".to_string()))?;
+    let bytes = URL_SAFE_NO_PAD
+        .decode(header)
+        .map_err(|e| OpenAIAuthError::InvalidJwt(format!("invalid JWT header: {}", e)))?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| OpenAIAuthError::InvalidJwt(format!("invalid JWT header: {}", e)))
+}
+
+/// Decode the claims of a JWT without verifying its signature
+///
+/// The `exp` claim is only checked if `validate_exp` is set.
+fn decode_claims(token: &str, validate_exp: bool) -> Result<Claims> {
+    // Decode without verification (we just need claims)
+    // The token comes from OpenAI's OAuth flow, so we trust it. Use the
+    // token's own algorithm so non-RS256 tokens decode too.
+    let header = jsonwebtoken::decode_header(token)?;
+    let mut validation = Validation::new(header.alg);
+    validation.insecure_disable_signature_validation();
+    validation.validate_exp = validate_exp;
+
+    Ok(decode::<Claims>(token, &DecodingKey::from_secret(&[]), &validation)?.claims)
+}
+
+/// Extract ChatGPT account ID from a token set
+///
+/// Tries the access token first and falls back to the id_token, since some
+/// token shapes only carry the account ID in the id_token.
+///
+/// # Errors
+///
+/// Returns the access token's error if neither token contains the account ID
+pub fn extract_account_id_from_tokens(tokens: &TokenSet) -> Result<String> {
+    extract_account_id(&tokens.access_token).or_else(|err| {
+        tokens
+            .id_token
+            .as_deref()
+            .and_then(|id_token| extract_account_id(id_token).ok())
+            .ok_or(err)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::unsigned_jwt;
+
+    #[test]
+    fn test_decode_jwt_header() {
+        use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+
+        let header = decode_jwt_header(&unsigned_jwt(r#"{"exp":0}"#)).unwrap();
+        assert_eq!(header.alg, "RS256");
+        assert_eq!(header.typ.as_deref(), Some("JWT"));
+        assert_eq!(header.kid, None);
+
+        let token = format!(
+            "{}.e30.",
+            URL_SAFE_NO_PAD.encode(r#"{"alg":"ES256","kid":"key-1"}"#)
+        );
+        let header = decode_jwt_header(&token).unwrap();
+        assert_eq!(header.alg, "ES256");
+        assert_eq!(header.kid.as_deref(), Some("key-1"));
+
+        assert!(matches!(
+            decode_jwt_header("sk-not-a-jwt"),
+            Err(OpenAIAuthError::InvalidJwt(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_claims_any_algorithm() {
+        use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+
+        let claims = r#"{"exp":0,"https://api.openai.com/auth":{"chatgpt_account_id":"acc_es"}}"#;
+        for alg in ["ES256", "HS256", "PS512"] {
+            let token = format!(
+                "{}.{}.sig",
+                URL_SAFE_NO_PAD.encode(format!(r#"{{"alg":"{}","typ":"JWT"}}"#, alg)),
+                URL_SAFE_NO_PAD.encode(claims)
+            );
+            assert_eq!(extract_account_id(&token).unwrap(), "acc_es", "{}", alg);
+        }
+    }
+
+    #[test]
+    fn test_extract_account_id_checked_rejects_expired() {
+        let claims = |exp: u64| {
+            format!(
+                r#"{{"exp":{},"https://api.openai.com/auth":{{"chatgpt_account_id":"acc_1"}}}}"#,
+                exp
+            )
+        };
+        let expired = unsigned_jwt(&claims(1));
+        assert_eq!(extract_account_id(&expired).unwrap(), "acc_1");
+        assert!(extract_account_id_checked(&expired, true).is_err());
+
+        let valid = unsigned_jwt(&claims(u64::from(u32::MAX)));
+        assert_eq!(extract_account_id_checked(&valid, true).unwrap(), "acc_1");
+    }
+
+    #[cfg(any(feature = "async", feature = "blocking"))]
+    #[test]
+    fn test_extract_organizations() {
+        let token = unsigned_jwt(
+            r#"{"exp":0,"https://api.openai.com/auth":{"organizations":[{"id":"org-1","title":"Personal","role":"owner","is_default":true}]}}"#,
+        );
+        let orgs = extract_organizations(&token).unwrap();
+        assert_eq!(orgs.len(), 1);
+        assert_eq!(orgs[0].id, "org-1");
+        assert_eq!(orgs[0].title.as_deref(), Some("Personal"));
+        assert_eq!(orgs[0].role.as_deref(), Some("owner"));
+
+        let token = unsigned_jwt(r#"{"exp":0}"#);
+        assert!(extract_organizations(&token).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_flow_verifies_id_token_nonce() {
+        let config = crate::OAuthConfig::builder().oidc_nonce(true).build();
+        let flow = crate::AuthorizationUrlBuilder::new(&config)
+            .build()
+            .unwrap();
+        let nonce = flow.nonce.clone().unwrap();
+        let url = url::Url::parse(&flow.authorization_url).unwrap();
+        assert!(url.query_pairs().any(|(k, v)| k == "nonce" && v == nonce));
+
+        let mut tokens = TokenSet::builder(unsigned_jwt(r#"{"exp":0}"#), 0)
+            .id_token(unsigned_jwt(&format!(r#"{{"exp":0,"nonce":"{}"}}"#, nonce)))
+            .build();
+        flow.verify_nonce(&tokens).unwrap();
+
+        tokens.id_token = Some(unsigned_jwt(r#"{"exp":0,"nonce":"replayed"}"#));
+        assert!(matches!(
+            flow.verify_nonce(&tokens),
+            Err(OpenAIAuthError::NonceMismatch)
+        ));
+        tokens.id_token = None;
+        assert!(matches!(
+            flow.verify_nonce(&tokens),
+            Err(OpenAIAuthError::NonceMismatch)
+        ));
+
+        let plain = crate::AuthorizationUrlBuilder::new(&crate::OAuthConfig::default())
+            .build()
+            .unwrap();
+        assert_eq!(plain.nonce, None);
+        assert!(!plain.authorization_url.contains("nonce="));
+        plain.verify_nonce(&tokens).unwrap();
+    }
+
+    #[test]
+    fn test_extract_account_id_falls_back_to_id_token() {
+        let mut tokens = TokenSet::builder(unsigned_jwt(r#"{"sub":"user","exp":0}"#), 0)
+            .id_token(unsigned_jwt(
+                r#"{"exp":0,"https://api.openai.com/auth":{"chatgpt_account_id":"acc_123"}}"#,
+            ))
+            .build();
+        assert_eq!(extract_account_id_from_tokens(&tokens).unwrap(), "acc_123");
+
+        tokens.id_token = None;
+        assert!(extract_account_id_from_tokens(&tokens).is_err());
+    }
+
+    #[test]
+    fn test_extract_email_from_tokens() {
+        let mut tokens = TokenSet::builder(unsigned_jwt(r#"{"sub":"user","exp":0}"#), 0)
+            .id_token(unsigned_jwt(r#"{"exp":0,"email":"user@example.com"}"#))
+            .build();
+        assert_eq!(extract_email(&tokens.access_token).unwrap(), None);
+        assert_eq!(
+            extract_email_from_tokens(&tokens).unwrap().as_deref(),
+            Some("user@example.com")
+        );
+
+        tokens.id_token = None;
+        assert_eq!(extract_email_from_tokens(&tokens).unwrap(), None);
+    }
 
     #[test]
     fn test_extract_account_id_missing_claim() {
@@ -2,19 +2,25 @@
 //!
 //! A Rust library for OpenAI/ChatGPT OAuth 2.0 authentication with PKCE support.
 //!
-//! This library provides both synchronous (blocking) and asynchronous (runtime-agnostic)
+//! This library provides both synchronous (blocking) and asynchronous (tokio-based)
 //! APIs for authenticating with OpenAI's OAuth 2.0 endpoints.
 //!
 //! ## Features
 //!
-//! - **Async API** (default): Runtime-agnostic async operations
+//! - **Async API** (default): Async operations, requires a tokio runtime
 //! - **Blocking API** (optional): Blocking operations, no async runtime required
+//! - **Minimal HTTP** (optional): Blocking token calls over `ureq`, without reqwest (`minimal-http`)
 //! - **PKCE Support**: Secure PKCE (SHA-256) authentication flow
 //! - **Configurable**: Custom client IDs, endpoints, redirect URIs
 //! - **Browser Integration**: Auto-open browser for authorization (default)
 //! - **Callback Server**: Local server for automatic callback handling (optional, requires tokio)
+//! - **HTTPS Callback**: Serve the callback over TLS for `https://localhost` redirects (optional, `tls`)
+//! - **One-Call Login**: `OAuthClient::login` runs the whole browser flow (requires `callback-server`)
 //! - **JWT Utilities**: Extract ChatGPT account ID from access tokens
 //! - **API Key Exchange**: Exchange id_token for OpenAI API key (Codex CLI flow)
+//! - **Device Flow**: Headless authorization with code entry on another device (RFC 8628)
+//! - **Token Stores**: Refresh and persist tokens atomically across processes
+//! - **Request Middleware**: Authenticate `reqwest-middleware` clients with refreshable tokens (optional, `middleware`)
 //!
 //! ## Quick Start (Async API)
 //!
@@ -53,8 +59,21 @@
 //! }
 //! ```
 
+mod api_key;
+mod authorize;
+mod callback;
+mod clock;
+mod codex;
+mod credential;
+mod device;
 mod error;
 mod jwt;
+mod metrics;
+mod observer;
+pub mod pkce;
+mod provider;
+mod session;
+mod store;
 mod types;
 
 #[cfg(feature = "async")]
@@ -63,21 +82,90 @@ mod client;
 #[cfg(feature = "blocking")]
 pub mod blocking;
 
+#[cfg(feature = "minimal-http")]
+pub mod minimal;
+
 #[cfg(feature = "browser")]
 mod browser;
 
 #[cfg(feature = "callback-server")]
 mod server;
 
+#[cfg(feature = "callback-server")]
+mod login;
+
+#[cfg(all(feature = "cert-pinning", any(feature = "async", feature = "blocking")))]
+mod pinning;
+
+#[cfg(feature = "tls")]
+mod tls;
+
+#[cfg(feature = "openai-client")]
+mod api_config;
+
+#[cfg(feature = "middleware")]
+mod middleware;
+
+#[cfg(test)]
+mod testing;
+
+#[cfg(feature = "qr")]
+mod qr;
+
+#[cfg(feature = "stdin")]
+mod stdin;
+
 // Public API exports
-pub use error::{OpenAIAuthError, Result};
-pub use types::{OAuthConfig, OAuthConfigBuilder, OAuthFlow, TokenSet};
+pub use api_key::{ApiKeyInfo, LazyApiKey};
+pub use authorize::AuthorizationUrlBuilder;
+pub use callback::{code_from_env, parse_callback_url};
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use credential::{CredentialKind, classify_credential};
+pub use device::DeviceAuthorization;
+pub use error::{OAuthErrorCode, OpenAIAuthError, Result};
+pub use jwt::{JwtHeader, OrgInfo, decode_jwt_header};
+pub use metrics::{ExchangeMeta, FailureCategory, FlowTimings, Metrics, Operation};
+pub use observer::FlowObserver;
+pub use provider::{Provider, ProviderPreset};
+pub use store::{FileTokenStore, StoreLock, TokenStore};
+pub use types::{
+    AuditSummary, HttpTuning, OAuthConfig, OAuthConfigBuilder, OAuthFlow, Session, TokenSet,
+    TokenSetBuilder,
+};
+
+#[cfg(feature = "keyring")]
+pub use store::KeyringTokenStore;
 
 #[cfg(feature = "async")]
 pub use client::OAuthClient;
+#[cfg(feature = "async")]
+pub use tokio_util::sync::CancellationToken;
 
 #[cfg(feature = "browser")]
 pub use browser::open_browser;
 
+#[cfg(all(feature = "browser", feature = "async"))]
+pub use browser::open_browser_async;
+
+#[cfg(feature = "callback-server")]
+pub use server::{
+    CallbackEvent, CallbackServerHandle, ServerEvent, escape_html, run_callback_server,
+    run_callback_server_dynamic, run_callback_server_on_ports, run_callback_server_stream,
+    run_callback_server_until, run_callback_server_with_exchange, run_callback_server_with_html,
+    with_auto_close, with_callback_hook,
+};
+
 #[cfg(feature = "callback-server")]
-pub use server::{CallbackEvent, run_callback_server, run_callback_server_with_html};
+pub use login::{CallbackFlow, LoginOptions};
+
+#[cfg(feature = "tls")]
+pub use tls::run_callback_server_tls;
+
+#[cfg(feature = "stdin")]
+pub use stdin::read_code_from_stdin;
+
+#[cfg(feature = "openai-client")]
+pub use api_config::{ApiConfig, CHATGPT_API_BASE_URL, OPENAI_API_BASE_URL};
+
+#[cfg(feature = "middleware")]
+pub use middleware::OpenAiAuthMiddleware;
@@ -0,0 +1,411 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use url::{Host, Url};
+
+use crate::server::serve_on_ports;
+use crate::{
+    CallbackEvent, OAuthClient, OAuthFlow, OpenAIAuthError, Result, Session, with_auto_close,
+    with_callback_hook,
+};
+
+type HtmlResponder = Arc<dyn Fn(CallbackEvent) -> String + Send + Sync>;
+type UrlHandler = Arc<dyn Fn(&str) + Send + Sync>;
+type CallbackHook = Arc<dyn Fn(&CallbackEvent) + Send + Sync>;
+
+/// A flow with its callback server already listening
+///
+/// Returned by `OAuthClient::start_flow_with_fallback`.
+pub struct CallbackFlow<F> {
+    /// A client whose redirect URI uses the bound port; use it to exchange the code
+    pub client: OAuthClient,
+    /// The started flow
+    pub flow: OAuthFlow,
+    /// The port the callback server is listening on
+    pub port: u16,
+    /// Future that resolves to the authorization code
+    pub code: F,
+}
+
+/// Options for `OAuthClient::login`
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use openai_auth::LoginOptions;
+///
+/// let options = LoginOptions::default()
+///     .port(1455)
+///     .timeout(Duration::from_secs(120))
+///     .fetch_api_key(true);
+/// ```
+#[derive(Clone)]
+pub struct LoginOptions {
+    port: Option<u16>,
+    timeout: Duration,
+    fetch_api_key: bool,
+    open_browser: bool,
+    auto_close: bool,
+    html_responder: Option<HtmlResponder>,
+    on_authorization_url: Option<UrlHandler>,
+    on_callback: Option<CallbackHook>,
+}
+
+impl Default for LoginOptions {
+    fn default() -> Self {
+        Self {
+            port: None,
+            timeout: Duration::from_secs(300),
+            fetch_api_key: false,
+            open_browser: true,
+            auto_close: false,
+            html_responder: None,
+            on_authorization_url: None,
+            on_callback: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for LoginOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoginOptions")
+            .field("port", &self.port)
+            .field("timeout", &self.timeout)
+            .field("fetch_api_key", &self.fetch_api_key)
+            .field("open_browser", &self.open_browser)
+            .field("auto_close", &self.auto_close)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LoginOptions {
+    /// Set the callback server port (default: the configured `redirect_ports`,
+    /// or the port of the configured redirect URI)
+    ///
+    /// The redirect URI sent to OpenAI is adjusted to use this port.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Set how long to wait for the OAuth callback (default: 5 minutes)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Also exchange the id_token for an OpenAI API key (default: false)
+    pub fn fetch_api_key(mut self, fetch_api_key: bool) -> Self {
+        self.fetch_api_key = fetch_api_key;
+        self
+    }
+
+    /// Open the authorization URL in the default browser (default: true)
+    ///
+    /// Has no effect unless the `browser` feature is enabled.
+    pub fn open_browser(mut self, open_browser: bool) -> Self {
+        self.open_browser = open_browser;
+        self
+    }
+
+    /// Try to close the browser tab after a successful callback (default: false)
+    ///
+    /// See `with_auto_close` for browser limitations.
+    pub fn auto_close(mut self, auto_close: bool) -> Self {
+        self.auto_close = auto_close;
+        self
+    }
+
+    /// Set a custom HTML responder for the callback page
+    pub fn html(
+        mut self,
+        responder: impl Fn(CallbackEvent) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.html_responder = Some(Arc::new(responder));
+        self
+    }
+
+    /// Set a closure that runs for every callback before the HTML is rendered
+    ///
+    /// See `with_callback_hook`.
+    pub fn on_callback(mut self, hook: impl Fn(&CallbackEvent) + Send + Sync + 'static) -> Self {
+        self.on_callback = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set a handler that receives the authorization URL before waiting for the callback
+    ///
+    /// Use this to print the URL so the user can open it manually.
+    pub fn on_authorization_url(mut self, handler: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.on_authorization_url = Some(Arc::new(handler));
+        self
+    }
+}
+
+impl OAuthClient {
+    /// Run the complete browser login flow
+    ///
+    /// This starts the flow, starts the local callback server, opens the
+    /// browser, waits for the callback (up to the configured timeout),
+    /// exchanges the code for tokens, optionally fetches an API key, and
+    /// extracts the ChatGPT account ID.
+    ///
+    /// **Note:** This requires the `callback-server` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Login options (port, timeout, API key exchange, HTML)
+    ///
+    /// # Returns
+    ///
+    /// A `Session` holding the tokens and the ChatGPT account ID
+    ///
+    /// # Errors
+    ///
+    /// Each stage reports its own error:
+    /// - `BrowserLaunch` if the browser can't be opened, or
+    ///   `BrowserUnavailable` if there is none and no `on_authorization_url`
+    ///   handler was set to show the URL instead
+    /// - `CallbackServer` if the callback server fails
+    /// - `CallbackTimeout` if no valid callback arrives in time (failed
+    ///   callbacks are shown in the browser and can be retried until then)
+    /// - `Http` / `Network` if the token or API key exchange fails
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use openai_auth::{LoginOptions, OAuthClient, OAuthConfig};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = OAuthClient::new(OAuthConfig::default())?;
+    /// let options = LoginOptions::default().on_authorization_url(|url| println!("Visit: {}", url));
+    /// let session = client.login(options).await?;
+    /// println!("Logged in as {:?}", session.account_id);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn login(&self, options: LoginOptions) -> Result<Session> {
+        let ports = match options.port {
+            Some(port) => vec![port],
+            None => self.callback_ports()?,
+        };
+
+        let html: HtmlResponder = options
+            .html_responder
+            .clone()
+            .unwrap_or_else(|| Arc::new(crate::server::default_callback_html));
+        let html: HtmlResponder = if options.auto_close {
+            Arc::new(with_auto_close(move |e| html(e)))
+        } else {
+            html
+        };
+        let html: HtmlResponder = match options.on_callback.clone() {
+            Some(hook) => Arc::new(with_callback_hook(move |e| hook(e), move |e| html(e))),
+            None => html,
+        };
+        let CallbackFlow {
+            client, flow, code, ..
+        } = self.bind_callback_flow(&ports, html)?;
+
+        if let Some(handler) = &options.on_authorization_url {
+            handler(&flow.authorization_url);
+        }
+
+        #[cfg(feature = "browser")]
+        if options.open_browser {
+            match crate::open_browser_async(&flow.authorization_url).await {
+                // The handler already showed the URL, so the user can open it
+                Err(OpenAIAuthError::BrowserUnavailable(_))
+                    if options.on_authorization_url.is_some() => {}
+                result => result?,
+            }
+        }
+
+        let code = tokio::time::timeout(options.timeout, code)
+            .await
+            .map_err(|_| OpenAIAuthError::CallbackTimeout)??;
+
+        let mut tokens = client.exchange_code(&code, &flow.pkce_verifier).await?;
+        flow.verify_nonce(&tokens)?;
+        if options.fetch_api_key {
+            client.ensure_api_key(&mut tokens).await?;
+        }
+
+        Ok(Session::from_tokens(tokens))
+    }
+
+    /// Start the callback server on the first free port and start a matching flow
+    ///
+    /// Tries the configured `redirect_ports` in order (or just the port of the
+    /// redirect URI if none are configured), binds the first available one,
+    /// and starts a flow whose redirect URI uses that port. Use the returned
+    /// client to exchange the code, since its redirect URI matches the flow.
+    ///
+    /// **Note:** This requires the `callback-server` feature and must be
+    /// called from within a tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` if the redirect URI isn't an http:// URL, or
+    /// `CallbackServer` if none of the ports is available
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use openai_auth::{OAuthClient, OAuthConfig};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = OAuthConfig::builder().redirect_ports([1455, 1456, 1457]).build();
+    /// let client = OAuthClient::new(config)?;
+    ///
+    /// let callback = client.start_flow_with_fallback()?;
+    /// println!("Listening on port {}", callback.port);
+    /// println!("Visit: {}", callback.flow.authorization_url);
+    ///
+    /// let code = callback.code.await?;
+    /// let tokens = callback
+    ///     .client
+    ///     .exchange_code(&code, &callback.flow.pkce_verifier)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn start_flow_with_fallback(
+        &self,
+    ) -> Result<CallbackFlow<impl Future<Output = Result<String>> + use<>>> {
+        self.bind_callback_flow(
+            &self.callback_ports()?,
+            Arc::new(crate::server::default_callback_html),
+        )
+    }
+
+    /// The ports to try for the callback server
+    fn callback_ports(&self) -> Result<Vec<u16>> {
+        if !self.config.redirect_ports.is_empty() {
+            return Ok(self.config.redirect_ports.clone());
+        }
+        let port = self
+            .http_redirect_uri()?
+            .port_or_known_default()
+            .ok_or_else(|| {
+                OpenAIAuthError::InvalidConfig(format!(
+                    "Redirect URI {} has no port",
+                    self.config.redirect_uri
+                ))
+            })?;
+        Ok(vec![port])
+    }
+
+    /// The redirect URI, which must be http:// to be served by the callback server
+    ///
+    /// The code travels over plain http, so the host must be loopback
+    /// (RFC 8252 section 7.3); anything else could expose it on the network.
+    fn http_redirect_uri(&self) -> Result<Url> {
+        let redirect = Url::parse(&self.config.redirect_uri)?;
+        if redirect.scheme() != "http" {
+            return Err(OpenAIAuthError::InvalidConfig(format!(
+                "The callback server requires an http:// redirect URI, got {}",
+                self.config.redirect_uri
+            )));
+        }
+        let loopback = match redirect.host() {
+            Some(Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+            Some(Host::Ipv4(ip)) => ip.is_loopback(),
+            Some(Host::Ipv6(ip)) => ip.is_loopback(),
+            None => false,
+        };
+        if !loopback {
+            return Err(OpenAIAuthError::InvalidConfig(format!(
+                "The callback server requires a loopback redirect URI (localhost, 127.0.0.1 \
+                 or [::1]) since the code is sent over plain http, got {}",
+                self.config.redirect_uri
+            )));
+        }
+        Ok(redirect)
+    }
+
+    fn bind_callback_flow(
+        &self,
+        ports: &[u16],
+        html: HtmlResponder,
+    ) -> Result<CallbackFlow<impl Future<Output = Result<String>> + use<>>> {
+        let mut redirect = self.http_redirect_uri()?;
+        // Listen on the address the browser will connect to; localhost maps to 127.0.0.1
+        let ip = match redirect.host() {
+            Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+            Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+            _ => IpAddr::from([127, 0, 0, 1]),
+        };
+        let state = crate::types::generate_random_state(&self.config)?;
+        let (port, code) = serve_on_ports(ip, ports, redirect.path(), &state, html)?;
+
+        redirect.set_port(Some(port)).map_err(|_| {
+            OpenAIAuthError::InvalidConfig(format!(
+                "Cannot set port on redirect URI {}",
+                self.config.redirect_uri
+            ))
+        })?;
+        let client = self.with_redirect_uri(redirect.to_string());
+        let flow = client.start_flow_with_state(state)?;
+
+        Ok(CallbackFlow {
+            client,
+            flow,
+            port,
+            code,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OAuthConfig;
+
+    #[tokio::test]
+    async fn test_callback_server_rejects_non_loopback_redirect() {
+        for redirect_uri in [
+            "http://192.168.1.10:1455/auth/callback",
+            "http://auth.example.com:1455/auth/callback",
+        ] {
+            let config = OAuthConfig::builder().redirect_uri(redirect_uri).build();
+            let client = OAuthClient::new(config).unwrap();
+            assert!(matches!(
+                client.start_flow_with_fallback(),
+                Err(OpenAIAuthError::InvalidConfig(e)) if e.contains("loopback")
+            ));
+        }
+
+        for redirect_uri in [
+            "http://localhost:0/cb",
+            "http://127.0.0.1:0/cb",
+            "http://[::1]:0/cb",
+        ] {
+            let client = OAuthClient::default().with_redirect_uri(redirect_uri.to_string());
+            assert!(client.http_redirect_uri().is_ok(), "{}", redirect_uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_callback_server_listens_on_redirect_host() {
+        for redirect_uri in [
+            "http://127.0.0.1:0/auth/callback",
+            "http://[::1]:0/auth/callback",
+        ] {
+            let client = OAuthClient::default().with_redirect_uri(redirect_uri.to_string());
+            let callback = client.start_flow_with_fallback().unwrap();
+            assert_ne!(callback.port, 0);
+
+            let url = format!(
+                "{}?code=abc&state={}",
+                callback.client.config.redirect_uri, callback.flow.state
+            );
+            reqwest::get(url).await.unwrap();
+            assert_eq!(callback.code.await.unwrap(), "abc", "{}", redirect_uri);
+        }
+    }
+}
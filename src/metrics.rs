@@ -0,0 +1,182 @@
+use std::time::Duration;
+#[cfg(any(feature = "async", feature = "blocking"))]
+use std::time::Instant;
+
+use crate::OpenAIAuthError;
+#[cfg(any(feature = "async", feature = "blocking"))]
+use crate::Result;
+
+/// Token endpoint operation measured by `Metrics::request_latency`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// Authorization code exchange
+    Exchange,
+    /// Access token refresh
+    Refresh,
+    /// id_token to API key exchange
+    ApiKeyExchange,
+}
+
+/// Metrics hook for aggregate monitoring of token endpoint outcomes
+///
+/// Implement this trait to feed counters and latency histograms into a
+/// metrics system such as Prometheus, e.g. to alert when refreshes start
+/// failing across a fleet. All methods have no-op default implementations.
+///
+/// Register an implementation with `OAuthClient::with_metrics`.
+pub trait Metrics: Send + Sync {
+    /// Called when an authorization code was exchanged successfully
+    fn exchange_success(&self) {}
+
+    /// Called when an authorization code exchange failed, with the failure's category
+    fn exchange_failure(&self, _category: FailureCategory, _error: &OpenAIAuthError) {}
+
+    /// Called when tokens were refreshed successfully
+    fn refresh_success(&self) {}
+
+    /// Called when a token refresh failed, with the failure's category
+    fn refresh_failure(&self, _category: FailureCategory, _error: &OpenAIAuthError) {}
+
+    /// Called when an id_token was exchanged for an API key successfully
+    fn api_key_exchange_success(&self) {}
+
+    /// Called when an API key exchange failed, with the failure's category
+    fn api_key_exchange_failure(&self, _category: FailureCategory, _error: &OpenAIAuthError) {}
+
+    /// Called with the duration of every token endpoint operation
+    fn request_latency(&self, _operation: Operation, _latency: Duration) {}
+}
+
+/// Coarse category of a failed request, suitable as a metrics label
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FailureCategory {
+    /// The request didn't reach the server or no response arrived
+    Network,
+    /// The server rejected the request with a 4xx status
+    Rejected,
+    /// The server failed with a 5xx status
+    ServerError,
+    /// The server answered successfully but the body couldn't be parsed
+    InvalidResponse,
+    /// Any other failure, e.g. invalid local configuration
+    Other,
+}
+
+impl FailureCategory {
+    /// Categorize an error returned by a token endpoint operation
+    pub fn of(error: &OpenAIAuthError) -> Self {
+        let status = match error {
+            #[cfg(any(feature = "async", feature = "blocking"))]
+            OpenAIAuthError::Network(_) => return FailureCategory::Network,
+            #[cfg(feature = "minimal-http")]
+            OpenAIAuthError::Transport(_) => return FailureCategory::Network,
+            OpenAIAuthError::Serialization(_) => return FailureCategory::InvalidResponse,
+            OpenAIAuthError::AuthorizationCodeExpired { .. }
+            | OpenAIAuthError::InvalidApiKey(_) => {
+                return FailureCategory::Rejected;
+            }
+            OpenAIAuthError::Http { status, .. }
+            | OpenAIAuthError::ApiKeyExchange { status, .. }
+            | OpenAIAuthError::RefreshFailed { status, .. } => *status,
+            _ => return FailureCategory::Other,
+        };
+        match status {
+            400..=499 => FailureCategory::Rejected,
+            500..=599 => FailureCategory::ServerError,
+            _ => FailureCategory::InvalidResponse,
+        }
+    }
+
+    /// The category as a lowercase label, e.g. `server_error`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureCategory::Network => "network",
+            FailureCategory::Rejected => "rejected",
+            FailureCategory::ServerError => "server_error",
+            FailureCategory::InvalidResponse => "invalid_response",
+            FailureCategory::Other => "other",
+        }
+    }
+}
+
+/// Timing breakdown of a single token endpoint request
+///
+/// Returned by `OAuthClient::exchange_code_timed` to tell apart slow
+/// connections from slow servers. `dns` and `connect` are only filled in by
+/// backends that report them; reqwest doesn't, so with it they are `None`
+/// and that time is included in `time_to_first_byte`. Call
+/// `OAuthClient::warm_connection` beforehand to exclude it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlowTimings {
+    /// Time spent resolving the host name, if the backend reports it
+    pub dns: Option<Duration>,
+    /// Time spent establishing the connection, including the TLS handshake,
+    /// if the backend reports it
+    pub connect: Option<Duration>,
+    /// Time until the response headers arrived, including DNS resolution,
+    /// connection setup, TLS handshake and server processing
+    pub time_to_first_byte: Duration,
+    /// Time spent reading the response body
+    pub body: Duration,
+    /// Total request duration
+    pub total: Duration,
+}
+
+impl FlowTimings {
+    /// Record the time to first byte of a request started at `start`
+    #[cfg(any(feature = "async", feature = "blocking"))]
+    pub(crate) fn headers_received(&mut self, start: Instant) {
+        self.time_to_first_byte = start.elapsed();
+    }
+
+    /// Record the body and total duration of a request started at `start`
+    #[cfg(any(feature = "async", feature = "blocking"))]
+    pub(crate) fn finished(&mut self, start: Instant) {
+        self.total = start.elapsed();
+        self.body = self.total.saturating_sub(self.time_to_first_byte);
+    }
+}
+
+/// Details of a successful authorization code exchange
+///
+/// Returned by `OAuthClient::exchange_code_with_meta` for success-path
+/// telemetry, mirroring the status and request id that errors carry.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExchangeMeta {
+    /// HTTP status of the token response
+    pub status: u16,
+    /// The `x-request-id` response header, if present
+    pub request_id: Option<String>,
+    /// Duration of the whole exchange, including local validation
+    pub elapsed: Duration,
+    /// Timing breakdown of the token request
+    pub timings: FlowTimings,
+}
+
+/// Metrics implementation that ignores everything
+#[cfg(any(feature = "async", feature = "blocking"))]
+pub(crate) struct NoopMetrics;
+
+#[cfg(any(feature = "async", feature = "blocking"))]
+impl Metrics for NoopMetrics {}
+
+/// Report the outcome and latency of an operation started at `start`
+#[cfg(any(feature = "async", feature = "blocking"))]
+pub(crate) fn record<T>(
+    metrics: &dyn Metrics,
+    operation: Operation,
+    start: Instant,
+    result: &Result<T>,
+) {
+    metrics.request_latency(operation, start.elapsed());
+    match (operation, result) {
+        (Operation::Exchange, Ok(_)) => metrics.exchange_success(),
+        (Operation::Exchange, Err(e)) => metrics.exchange_failure(FailureCategory::of(e), e),
+        (Operation::Refresh, Ok(_)) => metrics.refresh_success(),
+        (Operation::Refresh, Err(e)) => metrics.refresh_failure(FailureCategory::of(e), e),
+        (Operation::ApiKeyExchange, Ok(_)) => metrics.api_key_exchange_success(),
+        (Operation::ApiKeyExchange, Err(e)) => {
+            metrics.api_key_exchange_failure(FailureCategory::of(e), e)
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! `reqwest-middleware` integration for authenticating API requests
+
+use std::sync::Arc;
+
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest_middleware::{Middleware, Next};
+use tokio::sync::Mutex;
+
+use crate::{OAuthClient, OpenAIAuthError, TokenSet};
+
+/// Middleware that authenticates every request with a refreshable token set
+///
+/// Before each request is sent, the tokens are refreshed with
+/// `OAuthClient::ensure_fresh` if they expire within the configured
+/// `OAuthConfig::expiry_buffer`, and the headers from
+/// `TokenSet::into_api_config` are added: `Authorization`, plus
+/// `chatgpt-account-id` for ChatGPT tokens. Concurrent requests share the
+/// token set, so an expired token is only refreshed once.
+///
+/// A failed refresh fails the request with `reqwest_middleware::Error::Middleware`
+/// wrapping the `OpenAIAuthError`; the request is not sent.
+///
+/// **Note:** This requires the `middleware` feature.
+///
+/// # Example
+///
+/// ```no_run
+/// # use openai_auth::{OAuthClient, OAuthConfig, OpenAiAuthMiddleware, TokenSet};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let tokens = TokenSet::builder("access", 0).refresh_token("refresh").build();
+/// let client = OAuthClient::new(OAuthConfig::default())?;
+/// let auth = OpenAiAuthMiddleware::new(client, tokens);
+///
+/// let http = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+///     .with(auth.clone())
+///     .build();
+/// let response = http
+///     .get("https://chatgpt.com/backend-api/codex/models")
+///     .send()
+///     .await?;
+///
+/// // Persist the tokens, which may have been refreshed
+/// let tokens = auth.tokens().await;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct OpenAiAuthMiddleware {
+    client: OAuthClient,
+    tokens: Arc<Mutex<TokenSet>>,
+}
+
+impl OpenAiAuthMiddleware {
+    /// Create a middleware that refreshes `tokens` with `client`
+    pub fn new(client: OAuthClient, tokens: TokenSet) -> Self {
+        Self::from_shared(client, Arc::new(Mutex::new(tokens)))
+    }
+
+    /// Create a middleware using a token set shared with the caller
+    ///
+    /// Refreshed tokens are written back to `tokens`, so other code holding
+    /// the same `Arc` sees them.
+    pub fn from_shared(client: OAuthClient, tokens: Arc<Mutex<TokenSet>>) -> Self {
+        Self { client, tokens }
+    }
+
+    /// A copy of the current tokens, including any refresh done by the middleware
+    pub async fn tokens(&self) -> TokenSet {
+        self.tokens.lock().await.clone()
+    }
+
+    async fn headers(&self) -> crate::Result<Vec<(&'static str, String)>> {
+        let mut tokens = self.tokens.lock().await;
+        self.client.ensure_fresh(&mut tokens).await?;
+        Ok(tokens.clone().into_api_config().headers())
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for OpenAiAuthMiddleware {
+    async fn handle(
+        &self,
+        mut request: reqwest::Request,
+        extensions: &mut http::Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<reqwest::Response> {
+        let headers = self
+            .headers()
+            .await
+            .map_err(reqwest_middleware::Error::middleware)?;
+        for (name, value) in headers {
+            let invalid = || {
+                reqwest_middleware::Error::middleware(OpenAIAuthError::OAuth(format!(
+                    "invalid {} header",
+                    name
+                )))
+            };
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?;
+            let value = HeaderValue::from_str(&value).map_err(|_| invalid())?;
+            request.headers_mut().insert(name, value);
+        }
+        next.run(request, extensions).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OAuthConfig;
+    use crate::testing::{rejecting_endpoint, token_endpoint, unsigned_jwt};
+    use std::sync::Mutex as StdMutex;
+
+    /// The `Authorization` and `chatgpt-account-id` headers of a request
+    type AuthHeaders = (String, Option<String>);
+
+    /// Records the auth headers of each request instead of sending it
+    #[derive(Clone, Default)]
+    struct Capture(Arc<StdMutex<Vec<AuthHeaders>>>);
+
+    #[async_trait::async_trait]
+    impl Middleware for Capture {
+        async fn handle(
+            &self,
+            request: reqwest::Request,
+            _: &mut http::Extensions,
+            _: Next<'_>,
+        ) -> reqwest_middleware::Result<reqwest::Response> {
+            let header = |name| {
+                request
+                    .headers()
+                    .get(name)
+                    .map(|value: &HeaderValue| value.to_str().unwrap().to_string())
+            };
+            let authorization = header("authorization").unwrap();
+            self.0
+                .lock()
+                .unwrap()
+                .push((authorization, header("chatgpt-account-id")));
+            Ok(http::Response::new("").into())
+        }
+    }
+
+    fn http(
+        auth: OpenAiAuthMiddleware,
+        capture: Capture,
+    ) -> reqwest_middleware::ClientWithMiddleware {
+        reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(auth)
+            .with(capture)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_middleware_refreshes_once_and_adds_headers() {
+        let config = OAuthConfig::builder()
+            .token_url(token_endpoint(
+                1,
+                "200 OK",
+                r#"{"access_token":"fresh","refresh_token":"refresh-2","expires_in":3600}"#,
+            ))
+            .build();
+        let client = OAuthClient::new(config).unwrap();
+        let tokens = TokenSet::builder("stale", 0)
+            .refresh_token("refresh")
+            .build();
+        let auth = OpenAiAuthMiddleware::new(client, tokens);
+        let capture = Capture::default();
+        let http = http(auth.clone(), capture.clone());
+
+        // The endpoint only answers once, so the second request must reuse
+        // the refreshed tokens
+        for _ in 0..2 {
+            http.get("http://127.0.0.1/models").send().await.unwrap();
+        }
+        // "fresh" isn't a JWT, so there is no account id to send
+        let fresh = ("Bearer fresh".to_string(), None);
+        assert_eq!(*capture.0.lock().unwrap(), [fresh.clone(), fresh]);
+        assert_eq!(auth.tokens().await.refresh_token, "refresh-2");
+    }
+
+    #[tokio::test]
+    async fn test_middleware_adds_account_id_from_access_token() {
+        let access_token = unsigned_jwt(
+            r#"{"exp":1,"https://api.openai.com/auth":{"chatgpt_account_id":"acct-1"}}"#,
+        );
+        let tokens = TokenSet::builder(access_token.clone(), u64::MAX / 2)
+            .refresh_token("refresh")
+            .build();
+        let capture = Capture::default();
+        let http = http(
+            OpenAiAuthMiddleware::new(OAuthClient::default(), tokens),
+            capture.clone(),
+        );
+
+        http.get("http://127.0.0.1/models").send().await.unwrap();
+        assert_eq!(
+            *capture.0.lock().unwrap(),
+            [(
+                format!("Bearer {}", access_token),
+                Some("acct-1".to_string())
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_middleware_fails_request_when_refresh_fails() {
+        let config = OAuthConfig::builder()
+            .token_url(rejecting_endpoint(1))
+            .build();
+        let client = OAuthClient::new(config).unwrap();
+        let tokens = TokenSet::builder("stale", 0)
+            .refresh_token("refresh")
+            .build();
+        let capture = Capture::default();
+        let http = http(OpenAiAuthMiddleware::new(client, tokens), capture.clone());
+
+        let result = http.get("http://127.0.0.1/models").send().await;
+        let Err(reqwest_middleware::Error::Middleware(error)) = result else {
+            panic!("expected a middleware error");
+        };
+        assert!(matches!(
+            error.downcast_ref::<OpenAIAuthError>(),
+            Some(OpenAIAuthError::RefreshFailed { .. })
+        ));
+        assert!(capture.0.lock().unwrap().is_empty());
+    }
+}
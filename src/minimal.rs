@@ -0,0 +1,393 @@
+//! Blocking OAuth client backed by `ureq`
+//!
+//! A small alternative to `blocking::OAuthClient` for binaries that want to
+//! avoid reqwest's dependency tree, e.g. CLIs that only run the manual-entry
+//! flow. It covers the token endpoint calls: exchanging a code, refreshing,
+//! and the API key exchange. Build with `default-features = false` and only
+//! the `minimal-http` feature to drop reqwest entirely.
+
+use std::sync::Arc;
+
+use crate::error::{api_key_exchange_error, exchange_error, minimal_response_error, refresh_error};
+use crate::observer::{NoopObserver, check_lifetime};
+use crate::types::{TokenResponse, parse_json, token_exchange_params, token_form};
+use crate::{
+    AuthorizationUrlBuilder, Clock, FlowObserver, OAuthConfig, OAuthFlow, OpenAIAuthError, Result,
+    SystemClock, TokenSet,
+};
+
+/// Blocking OpenAI OAuth client using `ureq` for HTTP
+///
+/// Supports starting flows (`start_flow`, `start_flow_with_state`,
+/// `start_flow_from`), `exchange_code`, `complete_flow`, `refresh_token`,
+/// `ensure_fresh` and `obtain_api_key`, with the same behavior as their
+/// `blocking::OAuthClient` counterparts. Everything else, including token
+/// stores, sessions, headless login, metrics, PAR, the device flow,
+/// certificate pinning and `HttpTuning`, needs a reqwest-based client.
+///
+/// Cloning is cheap: clones share the configuration and the underlying
+/// `ureq::Agent`, and therefore its connection pool.
+///
+/// **Note:** This requires the `minimal-http` feature.
+///
+/// # Example
+///
+/// ```no_run
+/// use openai_auth::{OAuthConfig, minimal::OAuthClient};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let client = OAuthClient::new(OAuthConfig::default())?;
+///     let flow = client.start_flow()?;
+///     println!("Visit: {}", flow.authorization_url);
+///
+///     let tokens = client.exchange_code("code", &flow.pkce_verifier)?;
+///     println!("Access token expires in: {:?}", tokens.expires_in());
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct OAuthClient {
+    config: Arc<OAuthConfig>,
+    http: ureq::Agent,
+    observer: Arc<dyn FlowObserver>,
+    clock: Arc<dyn Clock>,
+}
+
+impl OAuthClient {
+    /// Create a new OAuth client with the given configuration
+    ///
+    /// The configured User-Agent, request timeout and connect timeout are
+    /// applied to every request.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ClientCreation` if certificate pins are configured, since
+    /// this client can't enforce them
+    pub fn new(config: OAuthConfig) -> Result<Self> {
+        if !config.cert_pins.is_empty() {
+            return Err(OpenAIAuthError::ClientCreation(
+                "certificate pinning isn't supported by the minimal-http client".to_string(),
+            ));
+        }
+        let mut builder = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_global(config.request_timeout)
+            .timeout_connect(config.connect_timeout);
+        if let Some(user_agent) = &config.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        Ok(Self {
+            config: Arc::new(config),
+            http: builder.build().into(),
+            observer: Arc::new(NoopObserver),
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    /// Register an observer that is notified at each step of the flow
+    ///
+    /// See `FlowObserver` for the available events.
+    pub fn with_observer(mut self, observer: impl FlowObserver + 'static) -> Self {
+        self.observer = Arc::new(observer);
+        self
+    }
+
+    /// Use `clock` instead of the system clock for expiry checks
+    ///
+    /// Affects `ensure_fresh` and the `expires_at` of new tokens.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Start the OAuth authorization flow
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` if `OAuthConfig::state_entropy_bytes` is below
+    /// the minimum of 16, or an error if the authorization URL is invalid
+    pub fn start_flow(&self) -> Result<OAuthFlow> {
+        self.start_flow_with_state(crate::types::generate_random_state(&self.config)?)
+    }
+
+    /// Start the OAuth authorization flow with a caller-provided CSRF state
+    pub fn start_flow_with_state(&self, state: impl Into<String>) -> Result<OAuthFlow> {
+        self.start_flow_from(self.authorization_url_builder().state(state))
+    }
+
+    /// Create an `AuthorizationUrlBuilder` for this client's configuration
+    ///
+    /// Pass the customized builder to `start_flow_from`.
+    pub fn authorization_url_builder(&self) -> AuthorizationUrlBuilder<'_> {
+        AuthorizationUrlBuilder::new(&self.config)
+    }
+
+    /// Start the OAuth authorization flow from a customized builder
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the builder fails (see `AuthorizationUrlBuilder::build`)
+    pub fn start_flow_from(&self, builder: AuthorizationUrlBuilder<'_>) -> Result<OAuthFlow> {
+        builder.build()
+    }
+
+    /// Exchange an authorization code for access and refresh tokens
+    ///
+    /// The code is cleaned up the same way as by `blocking::OAuthClient::exchange_code`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` if the PKCE verifier isn't valid per RFC 7636,
+    /// `AuthorizationCodeExpired` if the code expired, `OAuth` if no refresh
+    /// token was issued and `OAuthConfig::require_refresh_token` is set,
+    /// `Http` if the server rejects the exchange, or `Transport` if the
+    /// request fails
+    pub fn exchange_code(&self, code: &str, verifier: &str) -> Result<TokenSet> {
+        crate::types::validate_verifier(verifier)?;
+        self.observer.on_code_received();
+
+        let code = crate::types::sanitize_code(code);
+        let params = [
+            ("grant_type", "authorization_code"),
+            ("client_id", &self.config.client_id),
+            ("code", &code),
+            ("code_verifier", verifier),
+            ("redirect_uri", &self.config.redirect_uri),
+        ];
+
+        let mut response = self.token_request(&params)?;
+        if !response.status().is_success() {
+            return Err(minimal_response_error(response, exchange_error));
+        }
+
+        let body = response.body_mut().read_to_string()?;
+        let token_response: TokenResponse = parse_json(&body, OpenAIAuthError::TokenExchange)?;
+        let tokens = TokenSet::from_response(token_response, self.clock.now());
+        if !tokens.is_refreshable() {
+            if self.config.require_refresh_token {
+                return Err(OpenAIAuthError::OAuth(
+                    "offline_access not granted; no refresh token issued".to_string(),
+                ));
+            }
+            tracing::warn!("offline_access not granted; no refresh token issued");
+        }
+        check_lifetime(&*self.observer, &tokens, self.config.expiry_buffer);
+        self.observer.on_tokens_exchanged(&tokens);
+        Ok(tokens)
+    }
+
+    /// Complete a flow from the full redirect URL
+    ///
+    /// Extracts the code with `parse_callback_url`, validates its state
+    /// against `flow.state`, exchanges it, and checks the flow's nonce.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL can't be parsed, carries an OAuth error,
+    /// has a mismatched state or no code, if the exchange fails, or
+    /// `NonceMismatch` if the id_token doesn't carry the flow's nonce
+    pub fn complete_flow(&self, callback_url: &str, flow: &OAuthFlow) -> Result<TokenSet> {
+        let code = crate::parse_callback_url(callback_url, &flow.state)?;
+        let tokens = self.exchange_code(&code, &flow.pkce_verifier)?;
+        flow.verify_nonce(&tokens)?;
+        Ok(tokens)
+    }
+
+    /// Refresh an expired access token
+    ///
+    /// # Errors
+    ///
+    /// Returns `RefreshFailed` if the server rejects the refresh, or
+    /// `Transport` if the request fails
+    pub fn refresh_token(&self, refresh_token: &str) -> Result<TokenSet> {
+        let params = [
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", &self.config.client_id),
+        ];
+
+        let mut response = self.token_request(&params)?;
+        if !response.status().is_success() {
+            return Err(minimal_response_error(response, refresh_error));
+        }
+
+        let body = response.body_mut().read_to_string()?;
+        let token_response: TokenResponse = parse_json(&body, OpenAIAuthError::TokenRefresh)?;
+        let tokens = TokenSet::from_response(token_response, self.clock.now());
+        check_lifetime(&*self.observer, &tokens, self.config.expiry_buffer);
+        self.observer.on_refresh(&tokens);
+        Ok(tokens)
+    }
+
+    /// Refresh `tokens` in place if they expire within `OAuthConfig::expiry_buffer`
+    ///
+    /// # Errors
+    ///
+    /// Returns `OAuth("no refresh token available")` if the tokens need a
+    /// refresh but aren't refreshable, or an error if the refresh fails
+    pub fn ensure_fresh(&self, tokens: &mut TokenSet) -> Result<()> {
+        if !tokens.is_expired_at(self.clock.now(), self.config.expiry_buffer) {
+            return Ok(());
+        }
+        tokens.merge(self.refresh_token(tokens.usable_refresh_token()?)?);
+        Ok(())
+    }
+
+    /// Exchange an OpenAI id_token for an API key access token
+    ///
+    /// # Errors
+    ///
+    /// Returns `ApiKeyExchange` if the server rejects the exchange, or
+    /// `Transport` if the request fails
+    pub fn obtain_api_key(&self, id_token: &str) -> Result<String> {
+        #[derive(serde::Deserialize)]
+        struct ExchangeResponse {
+            access_token: String,
+        }
+
+        let params = token_exchange_params(&self.config, id_token, None);
+        let mut response = self.token_request(&params)?;
+        if !response.status().is_success() {
+            return Err(minimal_response_error(response, api_key_exchange_error));
+        }
+
+        let status = response.status().as_u16();
+        let request_id = crate::error::request_id(response.headers());
+        let body = response.body_mut().read_to_string()?;
+        let exchange: ExchangeResponse =
+            parse_json(&body, |body| OpenAIAuthError::ApiKeyExchange {
+                status,
+                error_code: None,
+                body,
+                request_id,
+            })?;
+        Ok(exchange.access_token)
+    }
+
+    /// POST a form to the token endpoint, including any configured extra
+    /// form parameters and headers
+    fn token_request(&self, params: &[(&str, &str)]) -> Result<ureq::http::Response<ureq::Body>> {
+        let mut request = self.http.post(&self.config.token_url);
+        for (name, value) in &self.config.extra_headers {
+            request = request.header(name, value);
+        }
+        Ok(request.send_form(token_form(params, &self.config.extra_token_params))?)
+    }
+}
+
+impl Default for OAuthClient {
+    fn default() -> Self {
+        Self::new(OAuthConfig::default()).expect("Failed to create OAuth client with defaults")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{endpoint, rejecting_endpoint, token_endpoint};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    const TOKENS: &str = r#"{"access_token":"access","refresh_token":"refresh","expires_in":3600}"#;
+
+    fn client(token_url: String) -> OAuthClient {
+        OAuthClient::new(OAuthConfig::builder().token_url(token_url).build()).unwrap()
+    }
+
+    #[test]
+    fn test_error_variant_per_operation() {
+        let client = client(rejecting_endpoint(3));
+        let flow = client.start_flow().unwrap();
+
+        let exchange = client.exchange_code("code", &flow.pkce_verifier);
+        assert!(matches!(
+            exchange,
+            Err(OpenAIAuthError::Http { status: 400, .. })
+        ));
+        let refresh = client.refresh_token("refresh");
+        assert!(matches!(
+            refresh,
+            Err(OpenAIAuthError::RefreshFailed { status: 400, .. })
+        ));
+        let api_key = client.obtain_api_key("id-token");
+        assert!(matches!(
+            api_key,
+            Err(OpenAIAuthError::ApiKeyExchange { status: 400, .. })
+        ));
+    }
+
+    #[test]
+    fn test_complete_flow_sends_form_and_parses_tokens() {
+        let client = client(endpoint(1, |body| {
+            assert!(body.contains("grant_type=authorization_code"), "{}", body);
+            assert!(body.contains("code=abc"), "{}", body);
+            ("200 OK", TOKENS.to_string())
+        }));
+        let flow = client.start_flow_with_state("s1").unwrap();
+
+        let forged = client.complete_flow(
+            "http://localhost:1455/auth/callback?code=abc&state=s2",
+            &flow,
+        );
+        assert!(matches!(forged, Err(OpenAIAuthError::OAuth(_))));
+
+        let tokens = client
+            .complete_flow(
+                "http://localhost:1455/auth/callback?code=abc&state=s1",
+                &flow,
+            )
+            .unwrap();
+        assert_eq!(tokens.access_token, "access");
+        assert_eq!(tokens.refresh_token, "refresh");
+    }
+
+    #[test]
+    fn test_ensure_fresh_refreshes_expired_tokens() {
+        let client = client(token_endpoint(1, "200 OK", TOKENS));
+        let mut tokens = TokenSet::builder("stale", 0).refresh_token("old").build();
+
+        client.ensure_fresh(&mut tokens).unwrap();
+        assert_eq!(tokens.access_token, "access");
+
+        // Fresh tokens don't hit the endpoint, which only answers once
+        client.ensure_fresh(&mut tokens).unwrap();
+    }
+
+    #[test]
+    fn test_short_lived_tokens_are_reported() {
+        struct CountingObserver(Arc<AtomicUsize>);
+
+        impl FlowObserver for CountingObserver {
+            fn on_short_lived_tokens(&self, _tokens: &TokenSet, _buffer: Duration) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let reports = Arc::new(AtomicUsize::new(0));
+        let client = client(token_endpoint(
+            2,
+            "200 OK",
+            r#"{"access_token":"access","refresh_token":"refresh","expires_in":60}"#,
+        ))
+        .with_observer(CountingObserver(reports.clone()));
+        let flow = client.start_flow().unwrap();
+
+        client.exchange_code("code", &flow.pkce_verifier).unwrap();
+        client.refresh_token("refresh").unwrap();
+        assert_eq!(reports.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_unreachable_endpoint_is_a_transport_error() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let client = client(format!("http://127.0.0.1:{}/oauth/token", port));
+        assert!(matches!(
+            client.refresh_token("refresh"),
+            Err(OpenAIAuthError::Transport(_))
+        ));
+    }
+}
@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use crate::{OAuthFlow, TokenSet};
+
+/// Observer for the steps of the OAuth flow
+///
+/// Implement this trait to drive progress UIs or audit logs from real flow
+/// events, without the crate depending on a logging framework. All methods
+/// have no-op default implementations, so only the events of interest need
+/// to be implemented.
+///
+/// Register an observer with `OAuthClient::with_observer`.
+///
+/// # Example
+///
+/// ```
+/// use openai_auth::{FlowObserver, TokenSet};
+///
+/// struct Progress;
+///
+/// impl FlowObserver for Progress {
+///     fn on_code_received(&self) {
+///         println!("Exchanging code…");
+///     }
+///
+///     fn on_tokens_exchanged(&self, tokens: &TokenSet) {
+///         println!("Logged in, token expires in {:?}", tokens.expires_in());
+///     }
+/// }
+/// ```
+pub trait FlowObserver: Send + Sync {
+    /// Called when an authorization flow has been started
+    fn on_flow_started(&self, _flow: &OAuthFlow) {}
+
+    /// Called when an authorization code is about to be exchanged for tokens
+    fn on_code_received(&self) {}
+
+    /// Called when an authorization code has been exchanged for tokens
+    fn on_tokens_exchanged(&self, _tokens: &TokenSet) {}
+
+    /// Called when tokens have been refreshed
+    fn on_refresh(&self, _tokens: &TokenSet) {}
+
+    /// Called when newly issued tokens already expire within `buffer`
+    ///
+    /// `buffer` is the configured `OAuthConfig::expiry_buffer`. Such tokens
+    /// are reported as expired from the start, so every `refresh_and_store`
+    /// refreshes them again. This usually means the server's `expires_in` is
+    /// shorter than the buffer and the buffer should be lowered.
+    fn on_short_lived_tokens(&self, _tokens: &TokenSet, _buffer: Duration) {}
+}
+
+/// Warn and notify `observer` if newly issued `tokens` live no longer than `buffer`
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+pub(crate) fn check_lifetime(observer: &dyn FlowObserver, tokens: &TokenSet, buffer: Duration) {
+    if tokens.lifetime_below_buffer(buffer) {
+        tracing::warn!(
+            lifetime_secs = tokens
+                .expires_at
+                .saturating_sub(tokens.issued_at.unwrap_or_default()),
+            buffer_secs = buffer.as_secs(),
+            "issued tokens expire within the expiry buffer and will always be refreshed"
+        );
+        observer.on_short_lived_tokens(tokens, buffer);
+    }
+}
+
+/// Observer that ignores all events
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+pub(crate) struct NoopObserver;
+
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+impl FlowObserver for NoopObserver {}
@@ -0,0 +1,277 @@
+use std::sync::Arc;
+
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, SignatureScheme};
+use sha2::{Digest, Sha256};
+
+use crate::{OAuthConfig, OpenAIAuthError, Result};
+
+/// Error raised by the TLS handshake when no certificate matches a pin
+#[derive(Debug)]
+struct PinMismatch;
+
+impl std::fmt::Display for PinMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("certificate pin mismatch")
+    }
+}
+
+impl std::error::Error for PinMismatch {}
+
+/// Certificate verifier that performs the normal WebPKI validation and then,
+/// for the pinned hosts only, requires one certificate in the chain to match
+/// a SPKI SHA-256 pin
+#[derive(Debug)]
+struct PinnedVerifier {
+    inner: Arc<dyn ServerCertVerifier>,
+    pins: Vec<[u8; 32]>,
+    hosts: Vec<String>,
+}
+
+impl PinnedVerifier {
+    fn is_pinned(&self, server_name: &ServerName<'_>) -> bool {
+        let host = match server_name {
+            ServerName::DnsName(name) => name.as_ref().to_ascii_lowercase(),
+            ServerName::IpAddress(ip) => std::net::IpAddr::from(*ip).to_string(),
+            _ => return true,
+        };
+        self.hosts.contains(&host)
+    }
+
+    fn matches_pin(&self, cert: &CertificateDer<'_>) -> bool {
+        let Ok(cert) = webpki::EndEntityCert::try_from(cert) else {
+            return false;
+        };
+        let digest: [u8; 32] = Sha256::digest(cert.subject_public_key_info().as_ref()).into();
+        self.pins.contains(&digest)
+    }
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+
+        if !self.is_pinned(server_name)
+            || std::iter::once(end_entity)
+                .chain(intermediates)
+                .any(|cert| self.matches_pin(cert))
+        {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::Other(rustls::OtherError(Arc::new(
+                PinMismatch,
+            ))))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Hosts whose certificates must match a pin: those of the auth endpoints
+///
+/// Other hosts the client talks to, such as `api_key_info_url`, only get
+/// the normal WebPKI validation.
+fn pinned_hosts(config: &OAuthConfig) -> Vec<String> {
+    std::iter::once(&config.token_url)
+        .chain(&config.par_endpoint)
+        .chain(&config.device_authorization_url)
+        .filter_map(|endpoint| {
+            let url = url::Url::parse(endpoint).ok()?;
+            // IPv6 hosts are bracketed in URLs but not in server names
+            Some(url.host_str()?.trim_matches(['[', ']']).to_string())
+        })
+        .collect()
+}
+
+/// Build a TLS config that trusts the Mozilla roots and enforces the
+/// configured pins for the auth endpoints' hosts
+pub(crate) fn pinned_tls_config(config: &OAuthConfig) -> Result<rustls::ClientConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+
+    let inner = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+        .build()
+        .map_err(|e| OpenAIAuthError::ClientCreation(e.to_string()))?;
+    let verifier = PinnedVerifier {
+        inner,
+        pins: config.cert_pins.clone(),
+        hosts: pinned_hosts(config),
+    };
+
+    Ok(rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| OpenAIAuthError::ClientCreation(e.to_string()))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth())
+}
+
+/// Check whether a request failed because of a certificate pin mismatch
+pub(crate) fn is_pin_mismatch(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(error);
+    while let Some(error) = source {
+        // `io::Error::source` skips the wrapped error, so look inside explicitly
+        let error = match error.downcast_ref::<std::io::Error>() {
+            Some(io) => match io.get_ref() {
+                Some(inner) => inner as &(dyn std::error::Error + 'static),
+                None => error,
+            },
+            None => error,
+        };
+        let pin_mismatch = match error.downcast_ref::<rustls::Error>() {
+            Some(rustls::Error::Other(other)) => other.0.is::<PinMismatch>(),
+            _ => error.is::<PinMismatch>(),
+        };
+        if pin_mismatch {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    /// Self-signed certificate for `localhost`
+    const CERT: &str = "MIIBfjCCASWgAwIBAgIUWmjwErGqU5vHpFPf53HNy/Aht2cwCgYIKoZIzj0EAwIwFDESMBAGA1UEAwwJbG9jYWxob3N0MCAXDTI2MTAxNjAzNTg0NloYDzIxMjYwOTIyMDM1ODQ2WjAUMRIwEAYDVQQDDAlsb2NhbGhvc3QwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAQSluP6NgxCmO0hDsVmI3Y60quDdn+2yJ5kjHLCmEeqfnFzSouqGw4oFypx0FSAMPQlOlWsU2xrlBL6RthPmAN9o1MwUTAdBgNVHQ4EFgQUgDjZDyaah1bfzADGpWPBfAzNuiMwHwYDVR0jBBgwFoAUgDjZDyaah1bfzADGpWPBfAzNuiMwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNHADBEAiAX/Y1XYbo9wkL4VKSbIDbKDSHZYw/msLd54yY6nmBqxwIgGHr9Scru/dYsVWCbrNvDhn6uFnMDpCF06Bkja3xqP0w=";
+
+    /// SPKI SHA-256 of `CERT`, computed with openssl
+    const CERT_PIN: &str = "fb5b05fdc012b68f3d149ddb0034e58511fea8c361edc8d4ba8b27f04c8b934b";
+
+    /// Stands in for WebPKI validation, which a self-signed certificate can't pass
+    #[derive(Debug)]
+    struct AcceptAll;
+
+    impl ServerCertVerifier for AcceptAll {
+        fn verify_server_cert(
+            &self,
+            _: &CertificateDer<'_>,
+            _: &[CertificateDer<'_>],
+            _: &ServerName<'_>,
+            _: &[u8],
+            _: UnixTime,
+        ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            _: &[u8],
+            _: &CertificateDer<'_>,
+            _: &DigitallySignedStruct,
+        ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            _: &[u8],
+            _: &CertificateDer<'_>,
+            _: &DigitallySignedStruct,
+        ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            Vec::new()
+        }
+    }
+
+    fn verifier(pin: [u8; 32]) -> PinnedVerifier {
+        let config = OAuthConfig::builder()
+            .token_url("https://localhost/oauth/token")
+            .build();
+        PinnedVerifier {
+            inner: Arc::new(AcceptAll),
+            pins: vec![pin],
+            hosts: pinned_hosts(&config),
+        }
+    }
+
+    fn verify(verifier: &PinnedVerifier, host: &str) -> std::result::Result<(), rustls::Error> {
+        let cert = base64::engine::general_purpose::STANDARD
+            .decode(CERT)
+            .unwrap();
+        let server_name = ServerName::try_from(host.to_string()).unwrap();
+        verifier
+            .verify_server_cert(
+                &CertificateDer::from(cert),
+                &[],
+                &server_name,
+                &[],
+                UnixTime::now(),
+            )
+            .map(|_| ())
+    }
+
+    fn pin() -> [u8; 32] {
+        let mut pin = [0u8; 32];
+        for (i, byte) in pin.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&CERT_PIN[i * 2..i * 2 + 2], 16).unwrap();
+        }
+        pin
+    }
+
+    #[test]
+    fn test_matching_pin_is_accepted() {
+        assert!(verify(&verifier(pin()), "localhost").is_ok());
+    }
+
+    #[test]
+    fn test_mismatched_pin_is_rejected() {
+        let err = verify(&verifier([0; 32]), "localhost").unwrap_err();
+        assert!(is_pin_mismatch(&err));
+
+        // rustls surfaces handshake errors to reqwest wrapped in an io::Error
+        let io = std::io::Error::new(std::io::ErrorKind::InvalidData, err);
+        assert!(is_pin_mismatch(&io));
+        assert!(!is_pin_mismatch(&std::io::Error::other(
+            "certificate pin mismatch"
+        )));
+    }
+
+    #[test]
+    fn test_unpinned_hosts_skip_the_pin_check() {
+        assert!(verify(&verifier([0; 32]), "api.openai.com").is_ok());
+    }
+}
@@ -0,0 +1,24 @@
+//! PKCE (RFC 7636) helpers
+
+use sha2::{Digest, Sha256};
+
+/// Compute the S256 PKCE challenge for a verifier
+///
+/// Returns the unpadded base64url encoding of the SHA-256 digest of
+/// `verifier`, as sent in the `code_challenge` parameter. Useful for split
+/// flows, or to check that an authorization URL matches a stored verifier.
+///
+/// # Example
+///
+/// ```
+/// use openai_auth::pkce;
+///
+/// let challenge = pkce::challenge_for("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk");
+/// assert_eq!(challenge, "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+/// ```
+pub fn challenge_for(verifier: &str) -> String {
+    use base64::{Engine as _, engine::general_purpose};
+
+    let digest = Sha256::digest(verifier.as_bytes());
+    general_purpose::URL_SAFE_NO_PAD.encode(digest)
+}
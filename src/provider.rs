@@ -0,0 +1,53 @@
+/// An OAuth provider preset
+///
+/// Bundles the provider-specific parts of an `OAuthConfig`: the client ID,
+/// the endpoints, the scopes and extra authorization parameters. Everything
+/// else in the config (PKCE, state, timeouts, storage) is generic OAuth.
+/// Use it with `OAuthConfig::for_provider` or `OAuthConfigBuilder::provider`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Provider {
+    /// OpenAI, with the Codex CLI's public client (the default)
+    #[default]
+    OpenAiCodex,
+    /// Any other OAuth 2.0 provider supporting the authorization code flow with PKCE
+    Custom(ProviderPreset),
+}
+
+/// The provider-specific values of an `OAuthConfig`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderPreset {
+    /// OAuth client ID
+    pub client_id: String,
+    /// Authorization endpoint URL
+    pub auth_url: String,
+    /// Token endpoint URL
+    pub token_url: String,
+    /// Scopes requested by the authorization URL
+    pub scopes: Vec<String>,
+    /// Extra query parameters of the authorization URL
+    pub authorization_params: Vec<(String, String)>,
+}
+
+impl Provider {
+    /// The values of this preset
+    pub fn preset(&self) -> ProviderPreset {
+        match self {
+            Provider::OpenAiCodex => ProviderPreset {
+                client_id: "app_EMoamEEZ73f0CkXaXp7hrann".to_string(),
+                auth_url: "https://auth.openai.com/oauth/authorize".to_string(),
+                token_url: "https://auth.openai.com/oauth/token".to_string(),
+                scopes: ["openid", "profile", "email", "offline_access"]
+                    .map(String::from)
+                    .to_vec(),
+                authorization_params: [
+                    ("id_token_add_organizations", "true"),
+                    ("codex_cli_simplified_flow", "true"),
+                    ("originator", "codex_cli_rs"),
+                ]
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .to_vec(),
+            },
+            Provider::Custom(preset) => preset.clone(),
+        }
+    }
+}
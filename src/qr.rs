@@ -0,0 +1,153 @@
+//! Render authorization URLs as QR codes in a terminal
+
+use qrcode::{Color, EcLevel, QrCode};
+
+use crate::{OAuthFlow, OpenAIAuthError, Result};
+
+/// Modules of light border around the code, the minimum the standard requires
+const QUIET_ZONE: usize = 4;
+
+impl OAuthFlow {
+    /// Render the authorization URL as a QR code for printing in a terminal
+    ///
+    /// Users can scan the code with a phone to authorize on a second device.
+    /// Each character covers two rows of modules using Unicode half blocks,
+    /// with light modules drawn as blocks, so the code scans correctly on
+    /// terminals with a dark background.
+    ///
+    /// **Note:** This requires the `qr` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` if the URL is too long to fit in a QR code
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use openai_auth::{OAuthClient, OAuthConfig};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = OAuthClient::new(OAuthConfig::default())?;
+    /// let flow = client.start_flow()?;
+    /// println!("Scan to sign in:\n{}", flow.authorization_qr()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn authorization_qr(&self) -> Result<String> {
+        Ok(to_terminal(&encode(self.authorization_url.as_bytes())?))
+    }
+}
+
+/// Encode bytes with error correction level L, which is all a URL needs
+///
+/// The smallest version that fits the data is used.
+fn encode(data: &[u8]) -> Result<QrCode> {
+    QrCode::with_error_correction_level(data, EcLevel::L).map_err(|_| {
+        OpenAIAuthError::InvalidConfig("authorization URL is too long for a QR code".to_string())
+    })
+}
+
+/// Draw `code` with Unicode half blocks, two rows of modules per line
+fn to_terminal(code: &QrCode) -> String {
+    let size = code.width();
+    let full = size + 2 * QUIET_ZONE;
+    let light = |x: usize, y: usize| {
+        let inside = QUIET_ZONE..QUIET_ZONE + size;
+        !(inside.contains(&x)
+            && inside.contains(&y)
+            && code[(x - QUIET_ZONE, y - QUIET_ZONE)] == Color::Dark)
+    };
+
+    let mut out = String::new();
+    for y in (0..full).step_by(2) {
+        for x in 0..full {
+            let top = light(x, y);
+            let bottom = y + 1 < full && light(x, y + 1);
+            out.push(match (top, bottom) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_picks_version_and_renders() {
+        // Version 1-L holds 17 bytes, so 19 bytes need version 2
+        assert_eq!(encode(&[b'x'; 17]).unwrap().width(), 21);
+        assert_eq!(encode(b"https://example.com").unwrap().width(), 25);
+
+        let url = OAuthFlow::from_parts(
+            format!(
+                "https://auth.openai.com/oauth/authorize?{}",
+                "x".repeat(500)
+            ),
+            "a".repeat(43),
+            "state",
+        );
+        let rendered = url.authorization_qr().unwrap();
+        let code = encode(url.authorization_url.as_bytes()).unwrap();
+        assert_eq!(
+            rendered.lines().count(),
+            (code.width() + 2 * QUIET_ZONE).div_ceil(2)
+        );
+
+        assert!(encode(&[b'x'; 3000]).is_err());
+    }
+
+    #[test]
+    fn test_golden_matrix() {
+        // Version 2-L with mask 6, checked against an independent decoder
+        let code = encode(b"https://example.com").unwrap();
+        let rows: Vec<String> = (0..code.width())
+            .map(|y| {
+                (0..code.width())
+                    .map(|x| {
+                        if code[(x, y)] == Color::Dark {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                "#######.#..##.#...#######",
+                "#.....#..##.....#.#.....#",
+                "#.###.#...#.###.#.#.###.#",
+                "#.###.#.....##..#.#.###.#",
+                "#.###.#..#.#.##.#.#.###.#",
+                "#.....#...#.#.#.#.#.....#",
+                "#######.#.#.#.#.#.#######",
+                "........#.##.#.##........",
+                "##.##.#..###.#..#.#.....#",
+                "#...#..###..######.#####.",
+                "#..#.##...##.#.###.###..#",
+                ".##.#....#####...###.####",
+                "###..#####.###.##.##....#",
+                "#.#..........####...#..#.",
+                "##.#.##....###.##.#.#####",
+                "#.###..#...#.....###.##.#",
+                "#.#.#.##..#...#.#####.##.",
+                "........#.#####.#...#.##.",
+                "#######....#....#.#.#...#",
+                "#.....#..##.#.###...#..#.",
+                "#.###.#.##..#########...#",
+                "#.###.#.#.#...#.###....##",
+                "#.###.#..#..#.##.#..#####",
+                "#.....#.###...##...##.###",
+                "#######.#.#..##.#.#..#..#",
+            ]
+        );
+    }
+}
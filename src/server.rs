@@ -1,30 +1,119 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tiny_http::{Request, Response, Server};
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc, oneshot};
 
-use crate::{OpenAIAuthError, Result};
+use crate::client::cancellable;
+use crate::{OAuthClient, OAuthFlow, OpenAIAuthError, Result, TokenSet};
 
 #[derive(Debug)]
-struct CallbackData {
+pub(crate) struct CallbackData {
     code: String,
     _state: String,
+    tokens: Option<TokenSet>,
 }
 
-struct ServerState {
-    tx: Mutex<Option<oneshot::Sender<Result<CallbackData>>>>,
-    expected_state: String,
-    html_responder: Arc<dyn Fn(CallbackEvent) -> String + Send + Sync>,
+/// Exchanges a received code for tokens on the server thread
+type CodeExchange = Arc<dyn Fn(&str) -> Result<TokenSet> + Send + Sync>;
+
+/// How often the callback server checks whether the caller stopped waiting
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Path the callback server answers on unless told otherwise
+pub(crate) const DEFAULT_CALLBACK_PATH: &str = "/auth/callback";
+
+pub(crate) struct ServerState {
+    pub(crate) tx: Mutex<Option<oneshot::Sender<Result<CallbackData>>>>,
+    pub(crate) expected_state: String,
+    pub(crate) path: String,
+    pub(crate) html_responder: Arc<dyn Fn(CallbackEvent) -> String + Send + Sync>,
+    pub(crate) exchange: Option<CodeExchange>,
+    /// Set to stop serving, for servers controlled by a `CallbackServerHandle`
+    pub(crate) stop: Option<Arc<AtomicBool>>,
+}
+
+impl ServerState {
+    /// Whether serving should stop: the code was delivered, the caller
+    /// stopped waiting, or the server was shut down
+    pub(crate) fn is_finished(&self) -> bool {
+        let waiting = self
+            .tx
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|tx| !tx.is_closed());
+        let stopped = self
+            .stop
+            .as_ref()
+            .is_some_and(|stop| stop.load(Ordering::Relaxed));
+        !waiting || stopped
+    }
 }
 
 /// Callback events for customizing the HTML response.
+///
+/// `account_id` is only set by `run_callback_server_with_exchange`, which
+/// exchanges the code before rendering the page. The `reason` of `Error`
+/// comes from the request, which any page can send, so pass it through
+/// `escape_html` before putting it in the page.
 #[derive(Debug, Clone)]
 pub enum CallbackEvent {
-    Success { code: String },
-    Error { reason: String },
+    Success {
+        code: String,
+        account_id: Option<String>,
+    },
+    Error {
+        reason: String,
+    },
     StateMismatch,
     MissingCode,
 }
 
+/// Lifecycle events of a streaming callback server
+///
+/// Sent by `run_callback_server_stream`.
+#[derive(Debug, Clone)]
+pub enum ServerEvent {
+    /// The server is listening on `port`
+    Started { port: u16 },
+    /// A callback was received and answered
+    Callback(CallbackEvent),
+    /// The server stopped, after a successful callback or a shutdown
+    Stopped,
+}
+
+/// Controls a callback server started by `run_callback_server_stream`
+///
+/// Dropping the handle shuts the server down.
+#[derive(Debug)]
+pub struct CallbackServerHandle {
+    port: u16,
+    stop: Arc<AtomicBool>,
+}
+
+impl CallbackServerHandle {
+    /// The port the server is listening on
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Stop the server
+    ///
+    /// The server finishes within a fraction of a second and then sends
+    /// `ServerEvent::Stopped`.
+    pub fn shutdown(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for CallbackServerHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
 /// Run a local OAuth callback server
 ///
 /// This starts a local HTTP server that listens for the OAuth callback.
@@ -43,13 +132,16 @@ pub enum CallbackEvent {
 ///
 /// The authorization code from the callback
 ///
+/// The server keeps running after a failed callback (an OAuth error, a
+/// state mismatch or a missing code) so the user can retry in the browser,
+/// and only resolves once a valid code arrives. Wrap the future in a timeout
+/// to give up eventually; dropping it shuts the server down.
+///
 /// # Errors
 ///
 /// Returns an error if:
 /// - The server fails to start
-/// - An OAuth error is received
-/// - The state token doesn't match
-/// - The callback times out
+/// - The authorization server requires user interaction (`prompt=none` flows)
 ///
 /// # Example
 ///
@@ -84,13 +176,69 @@ pub async fn run_callback_server_with_html(
     port: u16,
     expected_state: &str,
     html_responder: impl Fn(CallbackEvent) -> String + Send + Sync + 'static,
+) -> Result<String> {
+    run_callback_server_at(port, DEFAULT_CALLBACK_PATH, expected_state, html_responder).await
+}
+
+/// Run a local OAuth callback server until `shutdown` resolves
+///
+/// This behaves like `run_callback_server`, but stops listening as soon as
+/// the `shutdown` future resolves, e.g. a Ctrl-C handler, an app-wide
+/// shutdown broadcast, or the user closing the login dialog. The port is
+/// released shortly after, once the server thread notices the caller is
+/// gone.
+///
+/// **Note:** This must be called from within a tokio runtime.
+///
+/// # Arguments
+///
+/// * `port` - The port to listen on (e.g., 1455)
+/// * `expected_state` - The CSRF state token to validate against
+/// * `shutdown` - A future that resolves when the server should stop
+///
+/// # Errors
+///
+/// Returns `OpenAIAuthError::Cancelled` if `shutdown` resolves before a
+/// valid callback arrives, or any error `run_callback_server` can return
+///
+/// # Example
+///
+/// ```no_run
+/// use openai_auth::run_callback_server_until;
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let ctrl_c = async {
+///     let _ = tokio::signal::ctrl_c().await;
+/// };
+/// let code = run_callback_server_until(1455, "expected-state", ctrl_c).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn run_callback_server_until(
+    port: u16,
+    expected_state: &str,
+    shutdown: impl Future<Output = ()>,
+) -> Result<String> {
+    cancellable(run_callback_server(port, expected_state), shutdown).await
+}
+
+/// Run the callback server answering on a specific path
+pub(crate) async fn run_callback_server_at(
+    port: u16,
+    path: &str,
+    expected_state: &str,
+    html_responder: impl Fn(CallbackEvent) -> String + Send + Sync + 'static,
 ) -> Result<String> {
     let (tx, rx) = oneshot::channel();
 
     let state = Arc::new(ServerState {
         tx: Mutex::new(Some(tx)),
         expected_state: expected_state.to_string(),
+        path: path.to_string(),
         html_responder: Arc::new(html_responder),
+        exchange: None,
+        stop: None,
     });
 
     let addr = format!("127.0.0.1:{}", port);
@@ -99,9 +247,307 @@ pub async fn run_callback_server_with_html(
     tokio::task::spawn_blocking(move || run_sync_server(&addr, state));
 
     // Wait for callback
+    wait_for_callback(rx).await
+}
+
+/// Run a local OAuth callback server that also exchanges the code for tokens
+///
+/// Like `run_callback_server_with_html`, but once a valid code arrives the
+/// server exchanges it with `client` using the flow's PKCE verifier (and
+/// checks its nonce, if any) before answering the browser. The responder
+/// then gets `CallbackEvent::Success` with the ChatGPT account ID decoded
+/// from the tokens, so the success page can show the signed-in account. If
+/// the exchange fails, the responder gets `CallbackEvent::Error` and the
+/// error is returned.
+///
+/// **Note:** This must be called from within a tokio runtime.
+///
+/// # Arguments
+///
+/// * `port` - The port to listen on (e.g., 1455)
+/// * `client` - The client to exchange the code with
+/// * `flow` - The flow the user is authorizing
+/// * `html_responder` - Renders the HTML for each callback event
+///
+/// # Errors
+///
+/// Returns an error if the server fails to start or the exchange fails
+///
+/// # Example
+///
+/// ```no_run
+/// use openai_auth::{CallbackEvent, OAuthClient, OAuthConfig, run_callback_server_with_exchange};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = OAuthClient::new(OAuthConfig::default())?;
+/// let flow = client.start_flow()?;
+/// println!("Visit: {}", flow.authorization_url);
+///
+/// let tokens = run_callback_server_with_exchange(1455, &client, &flow, |event| match event {
+///     CallbackEvent::Success { account_id, .. } => {
+///         format!("<html><body>Signed in to {:?}</body></html>", account_id)
+///     }
+///     other => format!("<html><body>{:?}</body></html>", other),
+/// })
+/// .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn run_callback_server_with_exchange(
+    port: u16,
+    client: &OAuthClient,
+    flow: &OAuthFlow,
+    html_responder: impl Fn(CallbackEvent) -> String + Send + Sync + 'static,
+) -> Result<TokenSet> {
+    let (tx, rx) = oneshot::channel();
+
+    let handle = tokio::runtime::Handle::current();
+    let (client, exchange_flow) = (client.clone(), flow.clone());
+    let exchange: CodeExchange = Arc::new(move |code: &str| {
+        let tokens = handle.block_on(client.exchange_code(code, &exchange_flow.pkce_verifier))?;
+        exchange_flow.verify_nonce(&tokens)?;
+        Ok(tokens)
+    });
+
+    let state = Arc::new(ServerState {
+        tx: Mutex::new(Some(tx)),
+        expected_state: flow.state.clone(),
+        path: DEFAULT_CALLBACK_PATH.to_string(),
+        html_responder: Arc::new(html_responder),
+        exchange: Some(exchange),
+        stop: None,
+    });
+
+    let server = bind_server(&format!("127.0.0.1:{}", port))?;
+    tokio::task::spawn_blocking(move || serve_requests(server, state));
+
+    receive_callback(rx).await?.tokens.ok_or_else(|| {
+        OpenAIAuthError::CallbackServer("Callback finished without tokens".to_string())
+    })
+}
+
+/// Run a local OAuth callback server on an OS-assigned port
+///
+/// The server is bound to an ephemeral port on 127.0.0.1 before this function
+/// returns, so the port can be used to build the matching redirect URI
+/// (e.g. with `OAuthConfigBuilder::redirect_port`) before starting the flow.
+/// This avoids failures when port 1455 is already in use.
+///
+/// **Note:** This must be called from within a tokio runtime.
+///
+/// # Arguments
+///
+/// * `expected_state` - The CSRF state token to validate against
+///
+/// # Returns
+///
+/// The bound port and a future resolving to the authorization code
+///
+/// # Errors
+///
+/// Returns an error if the server can't be bound. The returned future fails
+/// like `run_callback_server`.
+///
+/// # Example
+///
+/// ```no_run
+/// use openai_auth::{OAuthClient, OAuthConfig, run_callback_server_dynamic};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let state = "a-random-unguessable-state";
+/// let (port, code_future) = run_callback_server_dynamic(state)?;
+///
+/// let config = OAuthConfig::builder().redirect_port(port).build();
+/// let client = OAuthClient::new(config)?;
+/// let flow = client.start_flow_with_state(state)?;
+///
+/// println!("Visit: {}", flow.authorization_url);
+/// let code = code_future.await?;
+/// let tokens = client.exchange_code(&code, &flow.pkce_verifier).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn run_callback_server_dynamic(
+    expected_state: &str,
+) -> Result<(u16, impl Future<Output = Result<String>> + use<>)> {
+    let server = bind_server("127.0.0.1:0")?;
+    let port = bound_port(&server)?;
+
+    let code_future = serve_bound(
+        server,
+        DEFAULT_CALLBACK_PATH,
+        expected_state,
+        Arc::new(default_callback_html),
+    );
+    Ok((port, code_future))
+}
+
+/// Run a local OAuth callback server that reports its events over a channel
+///
+/// Instead of a single future resolving to the code, every callback is sent
+/// as a `ServerEvent` on the returned channel, framed by `Started` and
+/// `Stopped`, so the server fits into event loops and GUI frameworks driven
+/// by message passing. The server stops after the first successful callback,
+/// or when the handle is shut down or dropped. Failed callbacks are shown in
+/// the browser and reported, and the user can retry.
+///
+/// The server runs on its own thread, so no async runtime is required.
+///
+/// # Arguments
+///
+/// * `port` - The port to listen on, or 0 for an OS-assigned port
+/// * `expected_state` - The CSRF state token to validate against
+///
+/// # Errors
+///
+/// Returns `CallbackServer` if the port can't be bound
+///
+/// # Example
+///
+/// ```no_run
+/// use openai_auth::{CallbackEvent, ServerEvent, run_callback_server_stream};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let (server, mut events) = run_callback_server_stream(1455, "expected-state")?;
+/// while let Some(event) = events.recv().await {
+///     match event {
+///         ServerEvent::Callback(CallbackEvent::Success { code, .. }) => println!("Code: {}", code),
+///         ServerEvent::Callback(other) => println!("Callback failed: {:?}", other),
+///         ServerEvent::Started { port } => println!("Listening on {}", port),
+///         ServerEvent::Stopped => break,
+///     }
+/// }
+/// # drop(server);
+/// # Ok(())
+/// # }
+/// ```
+pub fn run_callback_server_stream(
+    port: u16,
+    expected_state: &str,
+) -> Result<(CallbackServerHandle, mpsc::UnboundedReceiver<ServerEvent>)> {
+    let server = bind_server(&format!("127.0.0.1:{}", port))?;
+    let port = bound_port(&server)?;
+
+    let (events, events_rx) = mpsc::unbounded_channel();
+    let forward = events.clone();
+    let (tx, rx) = oneshot::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let state = Arc::new(ServerState {
+        tx: Mutex::new(Some(tx)),
+        expected_state: expected_state.to_string(),
+        path: DEFAULT_CALLBACK_PATH.to_string(),
+        html_responder: Arc::new(move |event: CallbackEvent| {
+            let _ = forward.send(ServerEvent::Callback(event.clone()));
+            default_callback_html(event)
+        }),
+        exchange: None,
+        stop: Some(stop.clone()),
+    });
+
+    let _ = events.send(ServerEvent::Started { port });
+    std::thread::spawn(move || {
+        // Keep the receiver so the server runs until the code is delivered
+        let _rx = rx;
+        serve_requests(server, state);
+        let _ = events.send(ServerEvent::Stopped);
+    });
+
+    Ok((CallbackServerHandle { port, stop }, events_rx))
+}
+
+/// Run a local OAuth callback server on the first available port
+///
+/// Tries each port in order and binds the first one that is free, so the
+/// flow still works when another instance already holds the preferred port.
+/// Build the redirect URI from the returned port before starting the flow.
+///
+/// **Note:** This must be called from within a tokio runtime.
+///
+/// # Arguments
+///
+/// * `ports` - The ports to try, in order of preference
+/// * `expected_state` - The CSRF state token to validate against
+///
+/// # Returns
+///
+/// The bound port and a future that resolves to the authorization code
+///
+/// # Errors
+///
+/// Returns the bind error of the last port if none of them is available.
+/// The returned future fails like `run_callback_server`.
+pub fn run_callback_server_on_ports(
+    ports: &[u16],
+    expected_state: &str,
+) -> Result<(u16, impl Future<Output = Result<String>> + use<>)> {
+    serve_on_ports(
+        IpAddr::from([127, 0, 0, 1]),
+        ports,
+        DEFAULT_CALLBACK_PATH,
+        expected_state,
+        Arc::new(default_callback_html),
+    )
+}
+
+/// Bind the first available port on `ip` and serve callbacks on `path`
+pub(crate) fn serve_on_ports(
+    ip: IpAddr,
+    ports: &[u16],
+    path: &str,
+    expected_state: &str,
+    html_responder: Arc<dyn Fn(CallbackEvent) -> String + Send + Sync>,
+) -> Result<(u16, impl Future<Output = Result<String>> + use<>)> {
+    let mut last_error = None;
+    for &port in ports {
+        match bind_server(&SocketAddr::new(ip, port).to_string()) {
+            Ok(server) => {
+                let port = bound_port(&server)?;
+                let code_future = serve_bound(server, path, expected_state, html_responder);
+                return Ok((port, code_future));
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error
+        .unwrap_or_else(|| OpenAIAuthError::CallbackServer("No callback ports to try".to_string())))
+}
+
+/// Serve callbacks on an already bound server
+fn serve_bound(
+    server: Server,
+    path: &str,
+    expected_state: &str,
+    html_responder: Arc<dyn Fn(CallbackEvent) -> String + Send + Sync>,
+) -> impl Future<Output = Result<String>> + use<> {
+    let (tx, rx) = oneshot::channel();
+    let state = Arc::new(ServerState {
+        tx: Mutex::new(Some(tx)),
+        expected_state: expected_state.to_string(),
+        path: path.to_string(),
+        html_responder,
+        exchange: None,
+        stop: None,
+    });
+
+    tokio::task::spawn_blocking(move || serve_requests(server, state));
+
+    wait_for_callback(rx)
+}
+
+pub(crate) async fn wait_for_callback(
+    rx: oneshot::Receiver<Result<CallbackData>>,
+) -> Result<String> {
+    receive_callback(rx)
+        .await
+        .map(|callback_data| callback_data.code)
+}
+
+async fn receive_callback(rx: oneshot::Receiver<Result<CallbackData>>) -> Result<CallbackData> {
     match rx.await {
-        Ok(Ok(callback_data)) => Ok(callback_data.code),
-        Ok(Err(e)) => Err(e),
+        Ok(result) => result,
         Err(_) => Err(OpenAIAuthError::CallbackServer(
             "Server shut down unexpectedly".to_string(),
         )),
@@ -109,15 +555,36 @@ pub async fn run_callback_server_with_html(
 }
 
 fn run_sync_server(addr: &str, state: Arc<ServerState>) -> Result<()> {
-    let server = Server::http(addr).map_err(|e| {
-        OpenAIAuthError::CallbackServer(format!("Failed to bind to {}: {}", addr, e))
-    })?;
+    let server = bind_server(addr)?;
+    serve_requests(server, state);
+    Ok(())
+}
 
-    for request in server.incoming_requests() {
-        let url = request.url();
+fn bind_server(addr: &str) -> Result<Server> {
+    Server::http(addr)
+        .map_err(|e| OpenAIAuthError::CallbackServer(format!("Failed to bind to {}: {}", addr, e)))
+}
 
-        // Only handle /auth/callback requests
-        if url.starts_with("/auth/callback") {
+/// The port `server` is listening on, which differs from the requested one for port 0
+fn bound_port(server: &Server) -> Result<u16> {
+    server
+        .server_addr()
+        .to_ip()
+        .map(|addr| addr.port())
+        .ok_or_else(|| OpenAIAuthError::CallbackServer("Server has no IP address".to_string()))
+}
+
+fn serve_requests(server: Server, state: Arc<ServerState>) {
+    while !state.is_finished() {
+        let request = match server.recv_timeout(POLL_INTERVAL) {
+            Ok(Some(request)) => request,
+            Ok(None) => continue,
+            Err(_) => break,
+        };
+        let path = request.url().split(['?', '#']).next().unwrap_or("");
+
+        // Only handle requests for the callback path
+        if path == state.path {
             let should_stop = handle_callback_request(request, &state);
             if should_stop {
                 break;
@@ -128,101 +595,250 @@ fn run_sync_server(addr: &str, state: Arc<ServerState>) -> Result<()> {
             let _ = request.respond(response);
         }
     }
-
-    Ok(())
 }
 
 fn handle_callback_request(request: Request, state: &Arc<ServerState>) -> bool {
-    // Parse query parameters from URL
-    let url = request.url();
-    let query_str = url.split('?').nth(1).unwrap_or("");
-    let params = querystring::querify(query_str);
-
-    // Extract parameters
-    let code = params
-        .iter()
-        .find(|(k, _)| *k == "code")
-        .map(|(_, v)| v.to_string());
-    let received_state = params
-        .iter()
-        .find(|(k, _)| *k == "state")
-        .map(|(_, v)| v.to_string());
-    let error = params
-        .iter()
-        .find(|(k, _)| *k == "error")
-        .map(|(_, v)| v.to_string());
-
-    // Process the callback and generate response
-    let (html, should_stop) = process_callback(code, received_state, error, state);
+    let query_str = request
+        .url()
+        .split_once('?')
+        .map_or("", |(_, query)| query.split('#').next().unwrap_or(""));
+    let (html, should_stop) = callback_response(query_str, state);
 
     // Send HTML response
-    let response = Response::from_string(html).with_header(
-        tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..])
-            .unwrap(),
+    let response = callback_headers(&html).into_iter().fold(
+        Response::from_string(html),
+        |response, (name, value)| {
+            response.with_header(tiny_http::Header::from_bytes(name, value).unwrap())
+        },
     );
 
     let _ = request.respond(response);
     should_stop
 }
 
+/// Headers of a callback page showing `html`
+///
+/// The page is never cached, since it is the target of the OAuth redirect,
+/// and may only use inline styles. Scripts are blocked, except for the
+/// `with_auto_close` script, which is allowed by its hash on pages that
+/// contain it.
+pub(crate) fn callback_headers(html: &str) -> [(&'static str, String); 4] {
+    let script_src = if html.contains(AUTO_CLOSE_SCRIPT) {
+        AUTO_CLOSE_SCRIPT_HASH
+    } else {
+        "'none'"
+    };
+    [
+        ("Content-Type", "text/html; charset=utf-8".to_string()),
+        ("Cache-Control", "no-store".to_string()),
+        ("Pragma", "no-cache".to_string()),
+        (
+            "Content-Security-Policy",
+            format!(
+                "default-src 'none'; style-src 'unsafe-inline'; script-src {}; \
+                 img-src data:; base-uri 'none'; form-action 'none'; frame-ancestors 'none'",
+                script_src
+            ),
+        ),
+    ]
+}
+
+/// Handle the query string of a callback request
+///
+/// Returns the HTML to show and whether the server should stop.
+pub(crate) fn callback_response(query_str: &str, state: &Arc<ServerState>) -> (String, bool) {
+    let query = crate::callback::CallbackQuery::parse(query_str);
+
+    // Process the callback and generate response
+    process_callback(query.code, query.state, query.error, state)
+}
+
+/// Process a callback hit, returning the HTML to show and whether to stop
+///
+/// Failed callbacks (an OAuth error, a state mismatch or a missing code) are
+/// shown to the user but don't resolve the server, so the user can retry the
+/// authorization in the browser. `AccessDenied`, returned when the user
+/// rejects the request, and `InteractionRequired`, returned for silent flows
+/// that have no UI to retry in, are reported immediately if they carry the
+/// expected state, so other pages can't abort a login in progress.
+/// Hits after the code has been delivered are ignored.
 fn process_callback(
     code: Option<String>,
     received_state: Option<String>,
     error: Option<String>,
     state: &Arc<ServerState>,
 ) -> (String, bool) {
+    let resolve = |result: Result<CallbackData>| {
+        if let Some(tx) = state.tx.lock().unwrap().take() {
+            let _ = tx.send(result);
+        }
+    };
+
+    // Validate state
+    let received_state_str = received_state.as_deref().unwrap_or("");
+    let state_matches = received_state_str == state.expected_state;
+
     // Check for OAuth errors
     if let Some(error) = error {
-        let _ = state.tx.lock().unwrap().take().map(|tx| {
-            tx.send(Err(OpenAIAuthError::OAuth(format!(
-                "OAuth error: {}",
-                error
-            ))))
-        });
+        let auth_error = crate::callback::authorization_error(error.clone());
+        let terminal = state_matches
+            && matches!(
+                auth_error,
+                OpenAIAuthError::AccessDenied | OpenAIAuthError::InteractionRequired(_)
+            );
+        if terminal {
+            resolve(Err(auth_error));
+        }
         return (
             (state.html_responder)(CallbackEvent::Error { reason: error }),
-            true,
+            terminal,
         );
     }
 
-    // Validate state
-    let received_state_str = received_state.as_deref().unwrap_or("");
-    if received_state_str != state.expected_state {
-        let _ = state.tx.lock().unwrap().take().map(|tx| {
-            tx.send(Err(OpenAIAuthError::OAuth(
-                "State mismatch - possible CSRF attack".to_string(),
-            )))
-        });
-        return ((state.html_responder)(CallbackEvent::StateMismatch), true);
+    if !state_matches {
+        return ((state.html_responder)(CallbackEvent::StateMismatch), false);
     }
 
-    // Extract code
+    // Extract code, exchanging it right away if requested
     match code {
         Some(code) => {
-            let _ = state.tx.lock().unwrap().take().map(|tx| {
-                tx.send(Ok(CallbackData {
-                    code: code.clone(),
-                    _state: received_state_str.to_string(),
-                }))
-            });
+            let tokens = match state.exchange.as_ref().map(|exchange| exchange(&code)) {
+                Some(Err(e)) => {
+                    // The error includes the response body, which isn't
+                    // for the browser; the caller gets it from the result
+                    let reason = match e.error_code() {
+                        Some(code) => format!("token exchange failed ({})", code.as_str()),
+                        None => "token exchange failed".to_string(),
+                    };
+                    resolve(Err(e));
+                    return (
+                        (state.html_responder)(CallbackEvent::Error { reason }),
+                        true,
+                    );
+                }
+                Some(Ok(tokens)) => Some(tokens),
+                None => None,
+            };
+            let account_id = tokens
+                .as_ref()
+                .and_then(|tokens| crate::jwt::extract_account_id_from_tokens(tokens).ok());
+            resolve(Ok(CallbackData {
+                code: code.clone(),
+                _state: received_state_str.to_string(),
+                tokens,
+            }));
             (
-                (state.html_responder)(CallbackEvent::Success { code }),
+                (state.html_responder)(CallbackEvent::Success { code, account_id }),
                 true,
             )
         }
-        None => {
-            let _ = state
-                .tx
-                .lock()
-                .unwrap()
-                .take()
-                .map(|tx| tx.send(Err(OpenAIAuthError::InvalidAuthorizationCode)));
-            ((state.html_responder)(CallbackEvent::MissingCode), true)
+        None => ((state.html_responder)(CallbackEvent::MissingCode), false),
+    }
+}
+
+const AUTO_CLOSE_SCRIPT: &str = "<script>window.close();</script>";
+
+/// CSP source allowing `AUTO_CLOSE_SCRIPT`: the SHA-256 of its content
+const AUTO_CLOSE_SCRIPT_HASH: &str = "'sha256-Wn5wULmWNAS84lRO4JMQ9jE/kYWz1ADWjWy4z2jjosA='";
+
+/// Wrap an HTML responder so the success page tries to close its browser tab
+///
+/// A `window.close()` script is inserted before `</body>` (or appended) on
+/// `CallbackEvent::Success`; other events are left unchanged. Browsers only
+/// allow scripts to close tabs that were opened by a script, so the tab
+/// usually stays open when the user opened the authorization URL manually.
+/// Callback pages block inline scripts, except for this one, which is
+/// allowed by its hash on the pages it is inserted into.
+///
+/// # Example
+///
+/// ```no_run
+/// use openai_auth::{run_callback_server_with_html, with_auto_close};
+///
+/// # async fn example() -> openai_auth::Result<()> {
+/// let code = run_callback_server_with_html(1455, "state", with_auto_close(|event| {
+///     format!("<html><body>{:?}</body></html>", event)
+/// }))
+/// .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn with_auto_close(
+    html_responder: impl Fn(CallbackEvent) -> String + Send + Sync + 'static,
+) -> impl Fn(CallbackEvent) -> String + Send + Sync + 'static {
+    move |event| {
+        let success = matches!(event, CallbackEvent::Success { .. });
+        let mut html = html_responder(event);
+        if success {
+            match html.rfind("</body>") {
+                Some(index) => html.insert_str(index, AUTO_CLOSE_SCRIPT),
+                None => html.push_str(AUTO_CLOSE_SCRIPT),
+            }
+        }
+        html
+    }
+}
+
+/// Run a closure for every callback before rendering the HTML response
+///
+/// Wraps an HTML responder so `on_callback` sees each `CallbackEvent` first,
+/// e.g. to log it, record metrics or show a desktop notification once
+/// authorization completes. The closure runs on the callback server's
+/// thread while the browser waits for the page, so keep it short.
+///
+/// # Example
+///
+/// ```no_run
+/// use openai_auth::{CallbackEvent, run_callback_server_with_html, with_callback_hook};
+///
+/// # async fn example() -> openai_auth::Result<()> {
+/// let html = with_callback_hook(
+///     |event| {
+///         if let CallbackEvent::Success { .. } = event {
+///             eprintln!("Authorization complete");
+///         }
+///     },
+///     |event| format!("<html><body>{:?}</body></html>", event),
+/// );
+/// let code = run_callback_server_with_html(1455, "state", html).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn with_callback_hook(
+    on_callback: impl Fn(&CallbackEvent) + Send + Sync + 'static,
+    html_responder: impl Fn(CallbackEvent) -> String + Send + Sync + 'static,
+) -> impl Fn(CallbackEvent) -> String + Send + Sync + 'static {
+    move |event| {
+        on_callback(&event);
+        html_responder(event)
+    }
+}
+
+/// Escape `text` for use in HTML content or attribute values
+///
+/// # Example
+///
+/// ```
+/// use openai_auth::escape_html;
+///
+/// assert_eq!(escape_html("<b>\"x\" & 'y'</b>"), "&lt;b&gt;&quot;x&quot; &amp; &#39;y&#39;&lt;/b&gt;");
+/// ```
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
         }
     }
+    escaped
 }
 
-fn default_callback_html(event: CallbackEvent) -> String {
+pub(crate) fn default_callback_html(event: CallbackEvent) -> String {
     match event {
         CallbackEvent::Success { .. } => r#"
             <html>
@@ -246,7 +862,7 @@ fn default_callback_html(event: CallbackEvent) -> String {
                 </body>
             </html>
             "#,
-            reason
+            escape_html(&reason)
         ),
         CallbackEvent::StateMismatch => r#"
             <html>
@@ -272,3 +888,317 @@ fn default_callback_html(event: CallbackEvent) -> String {
         .to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_callback_hook_runs_before_render() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let html = with_callback_hook(
+            move |event| log.lock().unwrap().push(format!("{:?}", event)),
+            default_callback_html,
+        );
+
+        let page = html(CallbackEvent::MissingCode);
+        assert!(!page.is_empty());
+        assert_eq!(*seen.lock().unwrap(), ["MissingCode"]);
+    }
+
+    #[test]
+    fn test_auto_close_only_on_success() {
+        let html = with_auto_close(default_callback_html);
+
+        let success = html(CallbackEvent::Success {
+            code: "abc".to_string(),
+            account_id: None,
+        });
+        assert!(success.contains("window.close()"));
+        assert!(success.find(AUTO_CLOSE_SCRIPT) < success.find("</body>"));
+
+        assert!(!html(CallbackEvent::MissingCode).contains("window.close()"));
+    }
+
+    #[tokio::test]
+    async fn test_error_reason_is_escaped() {
+        let (port, code_future) = run_callback_server_dynamic("expected").unwrap();
+        let url = format!(
+            "http://127.0.0.1:{}/auth/callback?error=%3Cscript%3Ealert(1)%3C/script%3E",
+            port
+        );
+
+        let response = reqwest::get(url).await.unwrap();
+        let csp = response.headers()["Content-Security-Policy"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(csp.contains("script-src 'none'"), "{}", csp);
+        let page = response.text().await.unwrap();
+        assert!(!page.contains("<script>"), "{}", page);
+        assert!(page.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        drop(code_future);
+    }
+
+    #[test]
+    fn test_csp_allows_only_auto_close_script() {
+        use base64::Engine;
+        use sha2::Digest;
+
+        let content = AUTO_CLOSE_SCRIPT
+            .strip_prefix("<script>")
+            .and_then(|script| script.strip_suffix("</script>"))
+            .unwrap();
+        let hash = base64::engine::general_purpose::STANDARD.encode(sha2::Sha256::digest(content));
+        assert_eq!(AUTO_CLOSE_SCRIPT_HASH, format!("'sha256-{}'", hash));
+
+        let csp = |html: &str| callback_headers(html)[3].1.clone();
+        let success = CallbackEvent::Success {
+            code: "abc".to_string(),
+            account_id: None,
+        };
+        assert!(csp(&default_callback_html(success.clone())).contains("script-src 'none'"));
+        let page = with_auto_close(default_callback_html)(success);
+        assert!(csp(&page).contains(&format!("script-src {};", AUTO_CLOSE_SCRIPT_HASH)));
+        assert!(!csp(&page).contains("script-src 'unsafe-inline'"));
+    }
+
+    #[tokio::test]
+    async fn test_server_falls_back_to_free_port() {
+        let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let taken_port = taken.local_addr().unwrap().port();
+        let free_port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let (port, code_future) =
+            run_callback_server_on_ports(&[taken_port, free_port], "expected").unwrap();
+        assert_eq!(port, free_port);
+
+        let url = format!(
+            "http://127.0.0.1:{}/auth/callback?code=abc&state=expected",
+            port
+        );
+        reqwest::get(url).await.unwrap();
+        assert_eq!(code_future.await.unwrap(), "abc");
+    }
+
+    #[tokio::test]
+    async fn test_server_accepts_retry_after_error() {
+        let (port, code_future) = run_callback_server_dynamic("expected").unwrap();
+        let callback = |query: &str| format!("http://127.0.0.1:{}/auth/callback?{}", port, query);
+
+        reqwest::get(callback("error=temporarily_unavailable&state=expected"))
+            .await
+            .unwrap();
+        reqwest::get(callback("code=stale&state=other"))
+            .await
+            .unwrap();
+        reqwest::get(callback("code=abc&state=expected"))
+            .await
+            .unwrap();
+
+        assert_eq!(code_future.await.unwrap(), "abc");
+    }
+
+    #[tokio::test]
+    async fn test_server_resolves_access_denied() {
+        let (port, code_future) = run_callback_server_dynamic("expected").unwrap();
+        let callback = |query: &str| format!("http://127.0.0.1:{}/auth/callback?{}", port, query);
+
+        // Errors without the expected state don't abort the flow
+        for query in [
+            "error=access_denied",
+            "error=access_denied&state=forged",
+            "error=login_required",
+        ] {
+            reqwest::get(callback(query)).await.unwrap();
+        }
+
+        reqwest::get(callback("error=access_denied&state=expected"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            code_future.await,
+            Err(OpenAIAuthError::AccessDenied)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_server_until_releases_port_on_shutdown() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let shutdown = tokio::time::sleep(Duration::from_millis(100));
+        let result = run_callback_server_until(port, "expected", shutdown).await;
+        assert!(matches!(result, Err(OpenAIAuthError::Cancelled)));
+
+        // The server thread notices the dropped receiver within one poll
+        let mut rebound = None;
+        for _ in 0..20 {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            if let Ok(listener) = std::net::TcpListener::bind(("127.0.0.1", port)) {
+                rebound = Some(listener);
+                break;
+            }
+        }
+        assert!(rebound.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_server_exchanges_code_and_reports_account() {
+        let access_token = crate::testing::unsigned_jwt(
+            r#"{"exp":1,"https://api.openai.com/auth":{"chatgpt_account_id":"acct-1"}}"#,
+        );
+        let body = format!(
+            r#"{{"access_token":"{}","refresh_token":"r"}}"#,
+            access_token
+        );
+        let token_url = crate::testing::endpoint(1, move |_| ("200 OK", body.clone()));
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let client =
+            OAuthClient::new(crate::OAuthConfig::builder().token_url(token_url).build()).unwrap();
+        let flow = client.start_flow_with_state("expected").unwrap();
+        let server = tokio::spawn(async move {
+            run_callback_server_with_exchange(port, &client, &flow, |event| format!("{:?}", event))
+                .await
+        });
+
+        let url = format!(
+            "http://127.0.0.1:{}/auth/callback?code=abc&state=expected",
+            port
+        );
+        let page = loop {
+            match reqwest::get(&url).await {
+                Ok(response) => break response.text().await.unwrap(),
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        };
+        assert!(page.contains(r#"account_id: Some("acct-1")"#), "{}", page);
+
+        let tokens = server.await.unwrap().unwrap();
+        assert_eq!(tokens.access_token, access_token);
+    }
+
+    #[tokio::test]
+    async fn test_exchange_server_reports_bind_failure() {
+        let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = taken.local_addr().unwrap().port();
+        let client = OAuthClient::new(crate::OAuthConfig::default()).unwrap();
+        let flow = client.start_flow_with_state("expected").unwrap();
+
+        let result =
+            run_callback_server_with_exchange(port, &client, &flow, default_callback_html).await;
+        assert!(
+            matches!(result, Err(OpenAIAuthError::CallbackServer(ref e)) if e.starts_with("Failed to bind")),
+            "{:?}",
+            result
+        );
+    }
+
+    #[tokio::test]
+    async fn test_server_hides_exchange_error_body() {
+        let token_url = crate::testing::token_endpoint(
+            1,
+            "400 Bad Request",
+            r#"{"error":"invalid_grant","error_description":"internal detail"}"#,
+        );
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let client =
+            OAuthClient::new(crate::OAuthConfig::builder().token_url(token_url).build()).unwrap();
+        let flow = client.start_flow_with_state("expected").unwrap();
+        let server = tokio::spawn(async move {
+            run_callback_server_with_exchange(port, &client, &flow, default_callback_html).await
+        });
+
+        let url = format!(
+            "http://127.0.0.1:{}/auth/callback?code=abc&state=expected",
+            port
+        );
+        let page = loop {
+            match reqwest::get(&url).await {
+                Ok(response) => break response.text().await.unwrap(),
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        };
+        assert!(
+            page.contains("token exchange failed (invalid_grant)"),
+            "{}",
+            page
+        );
+        assert!(!page.contains("internal detail"), "{}", page);
+
+        let error = server.await.unwrap().unwrap_err();
+        assert!(error.to_string().contains("internal detail"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_stream_server_reports_events_until_shutdown() {
+        let (server, mut events) = run_callback_server_stream(0, "expected").unwrap();
+        let port = server.port();
+        assert!(matches!(events.recv().await, Some(ServerEvent::Started { port: p }) if p == port));
+
+        let callback = |query: &str| format!("http://127.0.0.1:{}/auth/callback?{}", port, query);
+        reqwest::get(callback("code=abc&state=other"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            events.recv().await,
+            Some(ServerEvent::Callback(CallbackEvent::StateMismatch))
+        ));
+
+        server.shutdown();
+        assert!(matches!(events.recv().await, Some(ServerEvent::Stopped)));
+        assert!(events.recv().await.is_none());
+
+        let (server, mut events) = run_callback_server_stream(0, "expected").unwrap();
+        let url = format!(
+            "http://127.0.0.1:{}/auth/callback?code=abc&state=expected",
+            server.port()
+        );
+        reqwest::get(url).await.unwrap();
+        events.recv().await.unwrap();
+        assert!(matches!(
+            events.recv().await,
+            Some(ServerEvent::Callback(CallbackEvent::Success { code, .. })) if code == "abc"
+        ));
+        assert!(matches!(events.recv().await, Some(ServerEvent::Stopped)));
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_server_reports_port_and_receives_code() {
+        let (port, code_future) = run_callback_server_dynamic("expected").unwrap();
+        assert_ne!(port, 0);
+
+        let url = format!(
+            "http://127.0.0.1:{}/auth/callback?code=abc&state=expected",
+            port
+        );
+        let response = reqwest::get(url).await.unwrap();
+        assert!(response.status().is_success());
+        let html = default_callback_html(CallbackEvent::Success {
+            code: "abc".to_string(),
+            account_id: None,
+        });
+        for (name, value) in callback_headers(&html) {
+            assert_eq!(response.headers()[name], value);
+        }
+
+        assert_eq!(code_future.await.unwrap(), "abc");
+    }
+}
@@ -0,0 +1,61 @@
+use std::path::Path;
+
+use crate::store::{read_json, write_json};
+use crate::{Result, Session, TokenSet};
+
+impl Session {
+    /// Build a session from tokens, reading the account details from their claims
+    ///
+    /// Each of `account_id`, `email` and `plan_type` is read from the access
+    /// token, falling back to the id_token, and left `None` if neither
+    /// carries it.
+    pub fn from_tokens(tokens: TokenSet) -> Self {
+        Session {
+            account_id: crate::jwt::extract_account_id_from_tokens(&tokens).ok(),
+            email: crate::jwt::extract_email_from_tokens(&tokens)
+                .ok()
+                .flatten(),
+            plan_type: crate::jwt::extract_plan_type_from_tokens(&tokens),
+            tokens,
+        }
+    }
+
+    /// Save the session as JSON at `path`
+    ///
+    /// The write is atomic (write to a temporary file, then rename) and the
+    /// file is created readable by the owner only on Unix.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Storage` if the file can't be written
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_json(path.as_ref(), self)
+    }
+
+    /// Load a session saved with `save`, returning `None` if `path` doesn't exist
+    ///
+    /// The tokens are returned as stored, even if expired. Use
+    /// `OAuthClient::load_session` to refresh them when needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Storage` if the file can't be read, or `Serialization` if it
+    /// isn't a saved session
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Session>> {
+        read_json(path.as_ref())
+    }
+
+    /// Replace the tokens with refreshed ones, keeping cached details the new tokens lack
+    #[cfg(any(feature = "async", feature = "blocking"))]
+    pub(crate) fn refreshed(self, fresh: TokenSet) -> Session {
+        let mut tokens = self.tokens;
+        tokens.merge(fresh);
+        let session = Session::from_tokens(tokens);
+        Session {
+            account_id: session.account_id.or(self.account_id),
+            email: session.email.or(self.email),
+            plan_type: session.plan_type.or(self.plan_type),
+            ..session
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::{OpenAIAuthError, Result};
+
+/// Read an authorization code pasted on stdin, giving up after `timeout`
+///
+/// Reads a single line and cleans it up like `OAuthClient::exchange_code`
+/// does: whitespace is trimmed, and if the user pasted the full redirect URL
+/// or its query string, the `code` parameter is extracted. Use this instead
+/// of a bare `read_line` so unattended scripts don't hang forever.
+///
+/// The line is read on a background thread. If the timeout expires, that
+/// thread stays blocked on stdin until the next line or EOF.
+///
+/// **Note:** This requires the `stdin` feature.
+///
+/// # Errors
+///
+/// Returns `CallbackServer("timed out")` if no line arrives within `timeout`,
+/// `InvalidAuthorizationCode` if the line is empty or stdin is closed, or
+/// `CallbackServer` if reading stdin fails
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use openai_auth::read_code_from_stdin;
+///
+/// println!("Paste the authorization code:");
+/// let code = read_code_from_stdin(Duration::from_secs(300))?;
+/// # Ok::<(), openai_auth::OpenAIAuthError>(())
+/// ```
+pub fn read_code_from_stdin(timeout: Duration) -> Result<String> {
+    read_code_with(
+        || {
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            Ok(line)
+        },
+        timeout,
+    )
+}
+
+/// Run `read_line` on a background thread and sanitize its result
+fn read_code_with(
+    read_line: impl FnOnce() -> std::io::Result<String> + Send + 'static,
+    timeout: Duration,
+) -> Result<String> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(read_line());
+    });
+
+    let line = rx
+        .recv_timeout(timeout)
+        .map_err(|_| OpenAIAuthError::CallbackServer("timed out".to_string()))?
+        .map_err(|e| OpenAIAuthError::CallbackServer(format!("failed to read stdin: {}", e)))?;
+
+    crate::callback::code_from_value(&line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_code_sanitizes_and_times_out() {
+        let pasted = || Ok("http://localhost:1455/auth/callback?code=ac_1&state=s1\n".to_string());
+        assert_eq!(
+            read_code_with(pasted, Duration::from_secs(5)).unwrap(),
+            "ac_1"
+        );
+
+        let empty = || Ok(String::new());
+        assert!(matches!(
+            read_code_with(empty, Duration::from_secs(5)),
+            Err(OpenAIAuthError::InvalidAuthorizationCode)
+        ));
+
+        let never = || {
+            std::thread::sleep(Duration::from_secs(5));
+            Ok(String::new())
+        };
+        assert!(matches!(
+            read_code_with(never, Duration::from_millis(50)),
+            Err(OpenAIAuthError::CallbackServer(e)) if e == "timed out"
+        ));
+    }
+}
@@ -0,0 +1,348 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "async")]
+use std::time::Duration;
+
+use crate::{OpenAIAuthError, Result, TokenSet};
+
+/// Persistent storage for a `TokenSet`
+///
+/// Implement this trait to plug your own credential storage into
+/// `OAuthClient::refresh_and_store`. The crate ships a file-based
+/// implementation (`FileTokenStore`) and, with the `keyring` feature,
+/// a system keyring implementation (`KeyringTokenStore`).
+pub trait TokenStore {
+    /// Load the stored tokens, returning `None` if nothing has been stored yet
+    fn load(&self) -> Result<Option<TokenSet>>;
+
+    /// Persist the given tokens, replacing any previously stored value
+    fn save(&self, tokens: &TokenSet) -> Result<()>;
+
+    /// Acquire an exclusive lock on the store
+    ///
+    /// The lock is held until the returned `StoreLock` is dropped. It is used
+    /// to prevent several processes sharing the same store from refreshing
+    /// the same tokens concurrently. The default implementation does not lock.
+    fn lock(&self) -> Result<StoreLock> {
+        Ok(StoreLock::none())
+    }
+
+    /// Try to acquire an exclusive lock on the store without blocking
+    ///
+    /// Returns `None` if the lock is currently held elsewhere. The async
+    /// client uses this to wait for the lock without stalling the executor.
+    /// The default implementation delegates to `lock`.
+    fn try_lock(&self) -> Result<Option<StoreLock>> {
+        self.lock().map(Some)
+    }
+}
+
+/// Guard for an exclusive lock on a `TokenStore`
+///
+/// The lock is released when the guard is dropped.
+#[derive(Debug)]
+pub struct StoreLock {
+    _file: Option<File>,
+}
+
+impl StoreLock {
+    /// A guard that doesn't hold any lock
+    pub fn none() -> Self {
+        Self { _file: None }
+    }
+
+    /// Acquire an exclusive advisory lock on the given lock file
+    ///
+    /// The file is created if it doesn't exist. This call blocks until the
+    /// lock is available.
+    pub fn acquire(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = open_lock_file(path)?;
+        file.lock().map_err(|e| {
+            OpenAIAuthError::Storage(format!("Failed to lock {}: {}", path.display(), e))
+        })?;
+        Ok(Self { _file: Some(file) })
+    }
+
+    /// Try to acquire an exclusive advisory lock on the given lock file
+    ///
+    /// Returns `None` without blocking if another process holds the lock.
+    pub fn try_acquire(path: impl AsRef<Path>) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        let file = open_lock_file(path)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self { _file: Some(file) })),
+            Err(std::fs::TryLockError::WouldBlock) => Ok(None),
+            Err(std::fs::TryLockError::Error(e)) => Err(OpenAIAuthError::Storage(format!(
+                "Failed to lock {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+}
+
+fn open_lock_file(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .map_err(|e| OpenAIAuthError::Storage(format!("Failed to open {}: {}", path.display(), e)))
+}
+
+/// Acquire the store lock from async code without blocking the executor
+///
+/// Polls `TokenStore::try_lock`, backing off exponentially between attempts.
+#[cfg(feature = "async")]
+pub(crate) async fn lock_async(store: &impl TokenStore) -> Result<StoreLock> {
+    const MAX_BACKOFF: Duration = Duration::from_millis(500);
+
+    let mut backoff = Duration::from_millis(10);
+    loop {
+        if let Some(lock) = store.try_lock()? {
+            return Ok(lock);
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Token store backed by a JSON file
+///
+/// Writes are atomic (write to a temporary file, then rename), the file is
+/// created readable by the owner only on Unix, and locking
+/// uses an advisory lock on a sibling `<path>.lock` file, so the store can
+/// safely be shared between several processes.
+#[derive(Debug, Clone)]
+pub struct FileTokenStore {
+    path: PathBuf,
+}
+
+impl FileTokenStore {
+    /// Create a store that reads and writes tokens at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The path of the token file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl TokenStore for FileTokenStore {
+    fn load(&self) -> Result<Option<TokenSet>> {
+        read_json(&self.path)
+    }
+
+    fn save(&self, tokens: &TokenSet) -> Result<()> {
+        write_json(&self.path, tokens)
+    }
+
+    fn lock(&self) -> Result<StoreLock> {
+        StoreLock::acquire(sibling(&self.path, ".lock"))
+    }
+
+    fn try_lock(&self) -> Result<Option<StoreLock>> {
+        StoreLock::try_acquire(sibling(&self.path, ".lock"))
+    }
+}
+
+/// The path with `suffix` appended to its file name
+pub(crate) fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Read a JSON file, returning `None` if it doesn't exist
+pub(crate) fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(OpenAIAuthError::Storage(format!(
+            "Failed to read {}: {}",
+            path.display(),
+            e
+        ))),
+    }
+}
+
+/// Atomically replace a file with `value` as JSON, readable by the owner only
+///
+/// Each call writes to its own temporary file next to `path`, so concurrent
+/// writers never rename each other's partial writes into place.
+pub(crate) fn write_json(path: &Path, value: &impl serde::Serialize) -> Result<()> {
+    let tmp = sibling(
+        path,
+        &format!(".{}.{:08x}.tmp", std::process::id(), rand::random::<u32>()),
+    );
+    let json = serde_json::to_vec_pretty(value)?;
+
+    let write = || -> std::io::Result<()> {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&tmp)?;
+        file.write_all(&json)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    };
+
+    write().map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        OpenAIAuthError::Storage(format!("Failed to write {}: {}", path.display(), e))
+    })
+}
+
+/// Token store backed by the system keyring
+///
+/// The tokens are stored as a JSON string under the given service and user.
+/// Keyrings provide no locking primitive, so use `with_lock_file` when the
+/// entry is shared between processes.
+#[cfg(feature = "keyring")]
+pub struct KeyringTokenStore {
+    entry: keyring::Entry,
+    lock_file: Option<PathBuf>,
+}
+
+#[cfg(feature = "keyring")]
+impl KeyringTokenStore {
+    /// Create a store for the keyring entry identified by `service` and `user`
+    pub fn new(service: &str, user: &str) -> Result<Self> {
+        let entry = keyring::Entry::new(service, user)
+            .map_err(|e| OpenAIAuthError::Storage(format!("Failed to open keyring: {}", e)))?;
+        Ok(Self {
+            entry,
+            lock_file: None,
+        })
+    }
+
+    /// Guard refreshes with an advisory lock on the given file
+    pub fn with_lock_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.lock_file = Some(path.into());
+        self
+    }
+}
+
+#[cfg(feature = "keyring")]
+impl TokenStore for KeyringTokenStore {
+    fn load(&self) -> Result<Option<TokenSet>> {
+        match self.entry.get_password() {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(OpenAIAuthError::Storage(format!(
+                "Failed to read keyring: {}",
+                e
+            ))),
+        }
+    }
+
+    fn save(&self, tokens: &TokenSet) -> Result<()> {
+        let json = serde_json::to_string(tokens)?;
+        self.entry
+            .set_password(&json)
+            .map_err(|e| OpenAIAuthError::Storage(format!("Failed to write keyring: {}", e)))
+    }
+
+    fn lock(&self) -> Result<StoreLock> {
+        match &self.lock_file {
+            Some(path) => StoreLock::acquire(path),
+            None => Ok(StoreLock::none()),
+        }
+    }
+
+    fn try_lock(&self) -> Result<Option<StoreLock>> {
+        match &self.lock_file {
+            Some(path) => StoreLock::try_acquire(path),
+            None => Ok(Some(StoreLock::none())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_store_round_trip() {
+        let dir = std::env::temp_dir().join(format!("openai-auth-store-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let store = FileTokenStore::new(dir.join("tokens.json"));
+
+        assert!(store.load().unwrap().is_none());
+
+        let tokens = TokenSet::builder("access", 42)
+            .refresh_token("refresh")
+            .build();
+        let _lock = store.lock().unwrap();
+        store.save(&tokens).unwrap();
+
+        let loaded = store.load().unwrap().unwrap();
+        assert_eq!(loaded.access_token, "access");
+        assert_eq!(loaded.refresh_token, "refresh");
+        assert_eq!(loaded.expires_at, 42);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_writes_never_leave_a_partial_file() {
+        let dir =
+            std::env::temp_dir().join(format!("openai-auth-write-json-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.json");
+
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let path = &path;
+                scope.spawn(move || {
+                    let tokens = TokenSet::builder(format!("access-{}", i).repeat(1000), 42)
+                        .refresh_token("refresh")
+                        .build();
+                    for _ in 0..20 {
+                        write_json(path, &tokens).unwrap();
+                        let loaded: TokenSet = read_json(path).unwrap().unwrap();
+                        assert_eq!(loaded.expires_at, 42);
+                    }
+                });
+            }
+        });
+
+        // Only the final file is left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_try_lock_reports_contention() {
+        let dir = std::env::temp_dir().join(format!("openai-auth-try-lock-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let store = FileTokenStore::new(dir.join("tokens.json"));
+
+        let held = store.lock().unwrap();
+        assert!(store.try_lock().unwrap().is_none());
+        drop(held);
+        assert!(store.try_lock().unwrap().is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "keyring")]
+    #[test]
+    fn test_keyring_default_store_is_persistent() {
+        // Without a platform feature, keyring falls back to an in-memory mock
+        // that silently loses everything saved to it
+        let builder = keyring::default::default_credential_builder();
+        assert!(
+            builder
+                .as_any()
+                .downcast_ref::<keyring::mock::MockCredentialBuilder>()
+                .is_none(),
+            "keyring is using its mock credential store"
+        );
+    }
+}
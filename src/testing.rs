@@ -0,0 +1,77 @@
+//! Token endpoint stubs and token fixtures shared by the tests
+
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// An unsigned JWT carrying `claims`, for tests that only decode tokens
+pub(crate) fn unsigned_jwt(claims: &str) -> String {
+    use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+
+    format!(
+        "{}.{}.sig",
+        URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256","typ":"JWT"}"#),
+        URL_SAFE_NO_PAD.encode(claims)
+    )
+}
+
+/// Serve `count` requests with a 400 response on an ephemeral port
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+pub(crate) fn rejecting_endpoint(count: usize) -> String {
+    token_endpoint(count, "400 Bad Request", r#"{"error":"invalid_grant"}"#)
+}
+
+/// Serve `count` requests with the given response on an ephemeral port
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+pub(crate) fn token_endpoint(count: usize, status: &'static str, body: &'static str) -> String {
+    endpoint(count, move |_| (status, body.to_string()))
+}
+
+/// Serve `count` requests on an ephemeral port, answering each request body with `respond`
+///
+/// Every connection is handled on its own thread, so concurrent requests are
+/// served concurrently.
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+pub(crate) fn endpoint(
+    count: usize,
+    respond: impl Fn(&str) -> (&'static str, String) + Send + Sync + 'static,
+) -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/oauth/token", listener.local_addr().unwrap());
+    let respond = std::sync::Arc::new(respond);
+    std::thread::spawn(move || {
+        for stream in listener.incoming().take(count) {
+            let mut stream = stream.unwrap();
+            let respond = respond.clone();
+            std::thread::spawn(move || {
+                let mut reader = BufReader::new(&mut stream);
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                    if line.trim_end().is_empty() {
+                        break;
+                    }
+                }
+                let mut request = String::new();
+                reader
+                    .take(content_length)
+                    .read_to_string(&mut request)
+                    .unwrap();
+
+                let (status, body) = respond(&request);
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            });
+        }
+    });
+    url
+}
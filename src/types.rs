@@ -1,9 +1,18 @@
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+pub(crate) use crate::pkce::challenge_for as pkce_challenge;
+use crate::{OpenAIAuthError, Provider, Result};
 
 /// OAuth token set containing access token, refresh token, and expiration info
+///
+/// The struct is non-exhaustive so fields can be added without breaking
+/// callers; build tokens obtained elsewhere with `TokenSet::builder`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct TokenSet {
     /// The access token used to authenticate API requests
     pub access_token: String,
@@ -14,28 +23,233 @@ pub struct TokenSet {
     pub refresh_token: String,
     /// Unix timestamp (seconds) when the access token expires
     pub expires_at: u64,
+    /// Unix timestamp (seconds) when the access token was issued, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issued_at: Option<u64>,
+    /// Unix timestamp (seconds) when the refresh token expires, if the
+    /// server reported it with `refresh_expires_in`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_expires_at: Option<u64>,
     /// OpenAI API key derived from token exchange
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub api_key: Option<String>,
 }
 
 impl TokenSet {
+    /// Create a builder for tokens obtained elsewhere
+    ///
+    /// Use this to construct tokens loaded from another library or your own
+    /// storage, since `TokenSet` can't be built with a struct literal outside
+    /// this crate. The refresh token defaults to empty (not refreshable).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use openai_auth::TokenSet;
+    ///
+    /// let tokens = TokenSet::builder("access-token", 1_700_000_000)
+    ///     .refresh_token("refresh-token")
+    ///     .build();
+    /// assert!(tokens.is_refreshable());
+    /// ```
+    pub fn builder(access_token: impl Into<String>, expires_at: u64) -> TokenSetBuilder {
+        TokenSetBuilder {
+            tokens: TokenSet {
+                access_token: access_token.into(),
+                id_token: None,
+                refresh_token: String::new(),
+                expires_at,
+                issued_at: None,
+                refresh_expires_at: None,
+                api_key: None,
+            },
+        }
+    }
+
+    /// Merge the tokens from a refresh response into this set
+    ///
+    /// The access token and expiry are always replaced. The refresh token,
+    /// id_token and API key are only replaced when the fresh response
+    /// includes them, so values the server omits are carried forward. A
+    /// rotated refresh token takes its own expiry with it, if any.
+    pub fn merge(&mut self, fresh: TokenSet) {
+        self.access_token = fresh.access_token;
+        self.expires_at = fresh.expires_at;
+        self.issued_at = fresh.issued_at;
+        if !fresh.refresh_token.is_empty() {
+            self.refresh_token = fresh.refresh_token;
+            self.refresh_expires_at = fresh.refresh_expires_at;
+        } else if fresh.refresh_expires_at.is_some() {
+            self.refresh_expires_at = fresh.refresh_expires_at;
+        }
+        if fresh.id_token.is_some() {
+            self.id_token = fresh.id_token;
+        }
+        if fresh.api_key.is_some() {
+            self.api_key = fresh.api_key;
+        }
+    }
+
+    /// Check whether these tokens can be refreshed
+    ///
+    /// Token responses without a refresh token are stored with an empty
+    /// `refresh_token`, so this is `false` in that case.
+    pub fn is_refreshable(&self) -> bool {
+        !self.refresh_token.is_empty()
+    }
+
+    /// The refresh token, or an error if there is none
+    #[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+    pub(crate) fn usable_refresh_token(&self) -> Result<&str> {
+        if !self.is_refreshable() {
+            return Err(OpenAIAuthError::OAuth(
+                "no refresh token available".to_string(),
+            ));
+        }
+        Ok(&self.refresh_token)
+    }
+
+    /// Check whether an OpenAI API key has been obtained for these tokens
+    pub fn has_api_key(&self) -> bool {
+        self.api_key.is_some()
+    }
+
+    /// Get the OpenAI API key, if one has been obtained
+    pub fn api_key(&self) -> Option<&str> {
+        self.api_key.as_deref()
+    }
+
+    /// Get the access token masked for display, e.g. `eyJh...x9Qc`
+    ///
+    /// Only the first and last four characters are kept; tokens too short to
+    /// reveal anything safely are shown as `***`.
+    pub fn masked_access_token(&self) -> String {
+        mask_secret(&self.access_token)
+    }
+
+    /// Get the refresh token masked for display, like `masked_access_token`
+    pub fn masked_refresh_token(&self) -> String {
+        mask_secret(&self.refresh_token)
+    }
+
+    /// Get the OpenAI API key masked for display, like `masked_access_token`
+    pub fn masked_api_key(&self) -> Option<String> {
+        self.api_key.as_deref().map(mask_secret)
+    }
+
     /// Check if the token is expired or will expire soon (within 5 minutes)
     ///
     /// This includes a 5-minute buffer to prevent race conditions where a token
     /// expires between checking and using it.
     pub fn is_expired(&self) -> bool {
-        self.expires_in() <= Duration::from_secs(300)
+        self.is_expired_with_buffer(DEFAULT_EXPIRY_BUFFER)
+    }
+
+    /// Check whether the access token was issued with a lifetime within `buffer`
+    ///
+    /// Compares the lifetime at issue time (`expires_at - issued_at`) with
+    /// `buffer`, usually `OAuthConfig::expiry_buffer`, so the result doesn't
+    /// change as the token ages. If it returns `true`, the server issued
+    /// tokens that `is_expired_at` reports as expired from the start; lower
+    /// the buffer below the token lifetime in that case. Returns `false` if
+    /// the issue time is unknown.
+    pub fn lifetime_below_buffer(&self, buffer: Duration) -> bool {
+        self.issued_at
+            .is_some_and(|issued_at| self.expires_at.saturating_sub(issued_at) <= buffer.as_secs())
+    }
+
+    /// Check if the token is expired or will expire within `buffer`
+    ///
+    /// Use this with `OAuthConfig::expiry_buffer` to apply a buffer other
+    /// than the default 5 minutes.
+    pub fn is_expired_with_buffer(&self, buffer: Duration) -> bool {
+        self.is_expired_at(SystemTime::now(), buffer)
+    }
+
+    /// Check if the token is expired or will expire within `buffer` as of `now`
+    ///
+    /// Like `is_expired_with_buffer`, but with an explicit current time, e.g.
+    /// from a `Clock`.
+    pub fn is_expired_at(&self, now: SystemTime, buffer: Duration) -> bool {
+        self.expires_in_at(now) <= buffer
+    }
+
+    /// Get the time at which the access token expires
+    pub fn expires_at_time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.expires_at)
+    }
+
+    /// Get the time at which the access token expires as a UTC datetime
+    ///
+    /// **Note:** This requires the `time` feature.
+    #[cfg(feature = "time")]
+    pub fn expires_at_datetime(&self) -> time::OffsetDateTime {
+        time::OffsetDateTime::from(self.expires_at_time())
     }
 
     /// Get the duration until the token expires
     ///
     /// Returns `Duration::ZERO` if the token is already expired.
     pub fn expires_in(&self) -> Duration {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        self.expires_in_at(SystemTime::now())
+    }
+
+    /// Get how long to wait before refreshing, given an expiry `buffer`
+    ///
+    /// Tokens should be refreshed `buffer` before they expire, so this is
+    /// `expires_in() - buffer`, or `Duration::ZERO` if the tokens are already
+    /// within the buffer and should be refreshed now. Schedulers can sleep
+    /// for exactly this long.
+    pub fn time_until_refresh(&self, buffer: Duration) -> Duration {
+        self.time_until_refresh_at(SystemTime::now(), buffer)
+    }
+
+    /// Get how long to wait before refreshing as of `now`, given an expiry `buffer`
+    ///
+    /// Like `time_until_refresh`, but with an explicit current time, e.g.
+    /// from a `Clock`.
+    pub fn time_until_refresh_at(&self, now: SystemTime, buffer: Duration) -> Duration {
+        self.expires_in_at(now).saturating_sub(buffer)
+    }
+
+    /// Get the duration until the refresh token expires
+    ///
+    /// Returns `None` if the server didn't report a refresh token lifetime,
+    /// and `Duration::ZERO` once it has passed. Use it to warn users before
+    /// they have to log in again.
+    pub fn refresh_expires_in(&self) -> Option<Duration> {
+        self.refresh_expires_in_at(SystemTime::now())
+    }
+
+    /// Get the duration until the refresh token expires as of `now`
+    ///
+    /// Like `refresh_expires_in`, but with an explicit current time, e.g.
+    /// from a `Clock`.
+    pub fn refresh_expires_in_at(&self, now: SystemTime) -> Option<Duration> {
+        let now = unix_secs(now);
+        self.refresh_expires_at
+            .map(|expires_at| Duration::from_secs(expires_at.saturating_sub(now)))
+    }
+
+    /// Check whether the refresh token is known to have expired
+    ///
+    /// Returns `false` if the server didn't report a refresh token lifetime.
+    pub fn refresh_is_expired(&self) -> bool {
+        self.refresh_is_expired_at(SystemTime::now())
+    }
+
+    /// Check whether the refresh token is known to have expired as of `now`
+    ///
+    /// Returns `false` if the server didn't report a refresh token lifetime.
+    pub fn refresh_is_expired_at(&self, now: SystemTime) -> bool {
+        self.refresh_expires_in_at(now) == Some(Duration::ZERO)
+    }
+
+    /// Get the duration until the token expires as of `now`
+    ///
+    /// Returns `Duration::ZERO` if the token is already expired at `now`.
+    pub fn expires_in_at(&self, now: SystemTime) -> Duration {
+        let now = unix_secs(now);
 
         if self.expires_at > now {
             Duration::from_secs(self.expires_at - now)
@@ -45,51 +259,703 @@ impl TokenSet {
     }
 }
 
-/// OAuth authorization flow information
+/// Builder for TokenSet
 ///
-/// Contains the authorization URL and PKCE verifier needed to complete
-/// the OAuth flow.
+/// Created with `TokenSet::builder`.
 #[derive(Debug, Clone)]
+pub struct TokenSetBuilder {
+    tokens: TokenSet,
+}
+
+impl TokenSetBuilder {
+    /// Set the refresh token
+    pub fn refresh_token(mut self, refresh_token: impl Into<String>) -> Self {
+        self.tokens.refresh_token = refresh_token.into();
+        self
+    }
+
+    /// Set the id_token
+    pub fn id_token(mut self, id_token: impl Into<String>) -> Self {
+        self.tokens.id_token = Some(id_token.into());
+        self
+    }
+
+    /// Set the OpenAI API key
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.tokens.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Set the Unix timestamp (seconds) when the access token was issued
+    pub fn issued_at(mut self, issued_at: u64) -> Self {
+        self.tokens.issued_at = Some(issued_at);
+        self
+    }
+
+    /// Set the Unix timestamp (seconds) when the refresh token expires
+    pub fn refresh_expires_at(mut self, refresh_expires_at: u64) -> Self {
+        self.tokens.refresh_expires_at = Some(refresh_expires_at);
+        self
+    }
+
+    /// Build the TokenSet
+    pub fn build(self) -> TokenSet {
+        self.tokens
+    }
+}
+
+/// Keep the first and last four characters of a secret, eliding the rest
+fn mask_secret(secret: &str) -> String {
+    const KEEP: usize = 4;
+    let len = secret.chars().count();
+    if len < KEEP * 3 {
+        return "***".to_string();
+    }
+    let head: String = secret.chars().take(KEEP).collect();
+    let tail: String = secret.chars().skip(len - KEEP).collect();
+    format!("{}...{}", head, tail)
+}
+
+/// One-line summary without any secret material
+///
+/// Prints e.g. `TokenSet(expires in 58m, has_refresh, has_api_key)`.
+impl std::fmt::Display for TokenSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let secs = self.expires_in().as_secs();
+        match secs {
+            0 => write!(f, "TokenSet(expired")?,
+            1..60 => write!(f, "TokenSet(expires in {}s", secs)?,
+            60..3600 => write!(f, "TokenSet(expires in {}m", secs / 60)?,
+            _ => write!(
+                f,
+                "TokenSet(expires in {}h{}m",
+                secs / 3600,
+                secs % 3600 / 60
+            )?,
+        }
+        for (present, name) in [
+            (self.is_refreshable(), "has_refresh"),
+            (self.id_token.is_some(), "has_id_token"),
+            (self.api_key.is_some(), "has_api_key"),
+        ] {
+            if present {
+                write!(f, ", {}", name)?;
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+/// An authenticated session
+///
+/// Returned by `OAuthClient::login`, bundling the tokens with the account
+/// details read from their claims. Persist it with `Session::save` and
+/// reload it with `OAuthClient::load_session`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    /// The tokens obtained from the OAuth flow
+    pub tokens: TokenSet,
+    /// The ChatGPT account ID, if present in the tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<String>,
+    /// The user's email address, if present in the tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// The ChatGPT plan type (e.g. "plus"), if present in the tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan_type: Option<String>,
+}
+
+/// OAuth authorization flow information
+///
+/// Contains the authorization URL and PKCE parameters needed to complete
+/// the OAuth flow. It can be serialized to persist an in-progress flow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OAuthFlow {
     /// The URL the user should visit to authorize the application
     pub authorization_url: String,
     /// The PKCE verifier used to exchange the authorization code for tokens
     pub pkce_verifier: String,
+    /// The PKCE challenge (SHA-256 of the verifier) sent in the authorization URL
+    pub pkce_challenge: String,
     /// The CSRF state token for security validation
     pub state: String,
+    /// The OIDC nonce sent in the authorization URL, if `oidc_nonce` is enabled
+    ///
+    /// The id_token returned by the exchange must carry the same `nonce`
+    /// claim; see `OAuthFlow::verify_nonce`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+/// Default time before expiry at which tokens are treated as expired
+const DEFAULT_EXPIRY_BUFFER: Duration = Duration::from_secs(300);
+
+/// Default number of random bytes in a generated CSRF state
+const DEFAULT_STATE_ENTROPY_BYTES: usize = 32;
+
+/// Minimum number of random bytes in a generated CSRF state
+const MIN_STATE_ENTROPY_BYTES: usize = 16;
+
+/// Default number of random bytes in a generated PKCE verifier
+const DEFAULT_PKCE_VERIFIER_BYTES: usize = 32;
+
+/// Allowed random bytes in a PKCE verifier, which encode to the 43 to 128
+/// characters RFC 7636 allows
+const PKCE_VERIFIER_BYTES: std::ops::RangeInclusive<usize> = 32..=96;
+
+/// Maximum number of refreshes `refresh_many` runs at a time
+#[cfg(any(feature = "async", feature = "blocking"))]
+pub(crate) const MAX_PARALLEL_REFRESHES: usize = 8;
+
+/// Default User-Agent for HTTP requests
+const DEFAULT_USER_AGENT: &str = concat!("openai-auth/", env!("CARGO_PKG_VERSION"));
+
+impl OAuthFlow {
+    /// Reconstruct a flow from previously stored parts
+    ///
+    /// Use this to resume an interrupted flow, e.g. when the authorization URL
+    /// is opened on another machine and the code is exchanged by a later
+    /// process invocation. The PKCE challenge is recomputed from the verifier.
+    pub fn from_parts(
+        authorization_url: impl Into<String>,
+        pkce_verifier: impl Into<String>,
+        state: impl Into<String>,
+    ) -> Self {
+        let pkce_verifier = pkce_verifier.into();
+        Self {
+            authorization_url: authorization_url.into(),
+            pkce_challenge: pkce_challenge(&pkce_verifier),
+            pkce_verifier,
+            state: state.into(),
+            nonce: None,
+        }
+    }
+
+    /// Check the id_token's `nonce` claim against the nonce of this flow
+    ///
+    /// Does nothing if the flow has no nonce. `complete_flow` and `login`
+    /// call this after the exchange; call it yourself when exchanging with
+    /// `exchange_code` directly.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NonceMismatch` if the flow has a nonce and the token set has
+    /// no id_token, the id_token has no `nonce` claim, or the claim differs,
+    /// or an error if the id_token is malformed
+    pub fn verify_nonce(&self, tokens: &TokenSet) -> Result<()> {
+        let Some(expected) = &self.nonce else {
+            return Ok(());
+        };
+        let nonce = match tokens.id_token.as_deref() {
+            Some(id_token) => crate::jwt::extract_nonce(id_token)?,
+            None => None,
+        };
+        if nonce.as_ref() != Some(expected) {
+            return Err(OpenAIAuthError::NonceMismatch);
+        }
+        Ok(())
+    }
+
+    /// A copy of this flow with the authorization URL pointed at another host
+    ///
+    /// Retargets the flow to a regional or enterprise deployment without
+    /// starting a new flow. The scheme, path and all query parameters,
+    /// including the PKCE challenge and state, are kept, as are the
+    /// verifier and state of the flow.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` if `host` isn't a valid host name, or an error
+    /// if the authorization URL can't be parsed
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use openai_auth::OAuthFlow;
+    /// let flow = OAuthFlow::from_parts(
+    ///     "https://auth.openai.com/oauth/authorize?state=xyz",
+    ///     "a".repeat(43),
+    ///     "xyz",
+    /// );
+    /// let eu = flow.with_auth_host("auth.eu.example.com")?;
+    /// assert_eq!(eu.authorization_url, "https://auth.eu.example.com/oauth/authorize?state=xyz");
+    /// # Ok::<(), openai_auth::OpenAIAuthError>(())
+    /// ```
+    pub fn with_auth_host(&self, host: &str) -> Result<OAuthFlow> {
+        let mut url = Url::parse(&self.authorization_url)?;
+        if host.is_empty() || url.set_host(Some(host)).is_err() {
+            return Err(OpenAIAuthError::InvalidConfig(format!(
+                "invalid authorization host: {:?}",
+                host
+            )));
+        }
+
+        Ok(OAuthFlow {
+            authorization_url: url.into(),
+            ..self.clone()
+        })
+    }
+
+    /// Check that the authorization URL still matches `config` and this flow
+    ///
+    /// Re-parses the authorization URL and checks that each required
+    /// parameter appears exactly once with the expected value: `client_id`
+    /// and `redirect_uri` from the config, `response_type=code`,
+    /// `code_challenge_method=S256`, and the flow's `state` and PKCE
+    /// challenge (which must also match the verifier). Use it as a
+    /// self-check after transforming the URL, e.g. with `with_auth_host` or
+    /// by appending parameters. Pushed authorization request flows only
+    /// carry `client_id` and `request_uri`, so only those are checked.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` naming the first parameter that is missing,
+    /// repeated or wrong, or `UrlParse` if the URL can't be parsed
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use openai_auth::{AuthorizationUrlBuilder, OAuthConfig};
+    ///
+    /// let config = OAuthConfig::default();
+    /// let flow = AuthorizationUrlBuilder::new(&config).build()?;
+    /// flow.with_auth_host("auth.eu.example.com")?.validate_against(&config)?;
+    /// # Ok::<(), openai_auth::OpenAIAuthError>(())
+    /// ```
+    pub fn validate_against(&self, config: &OAuthConfig) -> Result<()> {
+        let url = Url::parse(&self.authorization_url)?;
+        let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        let param = |name: &str| -> Result<&str> {
+            let mut values = pairs.iter().filter(|(key, _)| key == name);
+            match (values.next(), values.next()) {
+                (Some((_, value)), None) => Ok(value),
+                (None, _) => Err(OpenAIAuthError::InvalidConfig(format!(
+                    "authorization URL has no {} parameter",
+                    name
+                ))),
+                (Some(_), Some(_)) => Err(OpenAIAuthError::InvalidConfig(format!(
+                    "authorization URL repeats the {} parameter",
+                    name
+                ))),
+            }
+        };
+        let expect = |name: &str, expected: &str| -> Result<()> {
+            let value = param(name)?;
+            if value != expected {
+                return Err(OpenAIAuthError::InvalidConfig(format!(
+                    "authorization URL has {}={:?}, expected {:?}",
+                    name, value, expected
+                )));
+            }
+            Ok(())
+        };
+
+        expect("client_id", &config.client_id)?;
+        if pairs.iter().any(|(key, _)| key == "request_uri") {
+            return param("request_uri").map(|_| ());
+        }
+        expect("redirect_uri", &config.redirect_uri)?;
+        expect("response_type", "code")?;
+        expect("code_challenge_method", "S256")?;
+        expect("state", &self.state)?;
+        expect("code_challenge", &self.pkce_challenge)?;
+        if self.pkce_challenge != pkce_challenge(&self.pkce_verifier) {
+            return Err(OpenAIAuthError::InvalidConfig(
+                "PKCE challenge doesn't match the verifier".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Summarize the non-secret parameters of this flow for audit logs
+    ///
+    /// The summary is parsed from the authorization URL and never includes
+    /// the PKCE verifier or the CSRF state, so it is safe to log.
+    pub fn audit_summary(&self) -> AuditSummary {
+        let Ok(url) = Url::parse(&self.authorization_url) else {
+            return AuditSummary::default();
+        };
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+
+        AuditSummary {
+            authorization_endpoint: Some(format!(
+                "{}{}",
+                url.origin().ascii_serialization(),
+                url.path()
+            )),
+            client_id: param("client_id"),
+            redirect_uri: param("redirect_uri"),
+            scopes: param("scope")
+                .map(|scope| scope.split_whitespace().map(str::to_string).collect())
+                .unwrap_or_default(),
+            code_challenge_method: param("code_challenge_method"),
+            prompt: param("prompt"),
+        }
+    }
+}
+
+/// Non-secret parameters of an `OAuthFlow`, safe to record in audit logs
+///
+/// Returned by `OAuthFlow::audit_summary`. Fields are `None` (or empty) when
+/// the authorization URL doesn't carry them, e.g. for pushed authorization
+/// requests.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AuditSummary {
+    /// The authorization endpoint, without query parameters
+    pub authorization_endpoint: Option<String>,
+    /// The OAuth client ID
+    pub client_id: Option<String>,
+    /// The redirect URI the code is sent to
+    pub redirect_uri: Option<String>,
+    /// The requested scopes
+    pub scopes: Vec<String>,
+    /// The PKCE challenge method (e.g. `S256`)
+    pub code_challenge_method: Option<String>,
+    /// The `prompt` parameter, if set (e.g. `none` for silent flows)
+    pub prompt: Option<String>,
 }
 
 /// Configuration for the OpenAI OAuth client
-#[derive(Debug, Clone)]
+///
+/// Serializes with snake_case field names. Missing fields deserialize to
+/// their defaults, so partial documents are accepted, and surrounding
+/// whitespace is trimmed from the URLs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "snake_case")]
 pub struct OAuthConfig {
     /// OAuth client ID (default: "app_EMoamEEZ73f0CkXaXp7hrann")
+    ///
+    /// This, the endpoints, `scopes` and `extra_authorization_params` come
+    /// from the `Provider` preset; see `OAuthConfig::for_provider`.
     pub client_id: String,
     /// Authorization endpoint URL
+    #[serde(deserialize_with = "deserialize_trimmed")]
     pub auth_url: String,
     /// Token exchange endpoint URL
+    #[serde(deserialize_with = "deserialize_trimmed")]
     pub token_url: String,
     /// Redirect URI for OAuth callback (default: "http://localhost:1455/auth/callback")
+    ///
+    /// Custom schemes such as `myapp://auth/callback` are allowed. Such flows
+    /// can't use the callback server; the OS must route the scheme back to the
+    /// application, which can then use `parse_callback_url`.
+    #[serde(deserialize_with = "deserialize_trimmed")]
     pub redirect_uri: String,
+    /// Scopes requested by the authorization URL
+    /// (default: "openid", "profile", "email", "offline_access")
+    pub scopes: Vec<String>,
+    /// Extra query parameters of the authorization URL
+    ///
+    /// The OpenAI preset sets the Codex CLI parameters
+    /// `id_token_add_organizations`, `codex_cli_simplified_flow` and
+    /// `originator`.
+    pub extra_authorization_params: Vec<(String, String)>,
+    /// Ports to try in order for the callback server (default: none)
+    ///
+    /// When set, the callback server binds the first free port and the
+    /// redirect URI is adjusted to it. See `OAuthClient::start_flow_with_fallback`.
+    pub redirect_ports: Vec<u16>,
+    /// Extra headers sent with every token endpoint request (e.g. for API gateways)
+    pub extra_headers: Vec<(String, String)>,
+    /// Extra form parameters sent with every token endpoint request
+    ///
+    /// A parameter with the same name as one set by the crate replaces it.
+    pub extra_token_params: Vec<(String, String)>,
+    /// Pushed authorization request endpoint URL (RFC 9126), if PAR should be used
+    #[serde(deserialize_with = "deserialize_trimmed_option")]
+    pub par_endpoint: Option<String>,
+    /// Device authorization endpoint URL for the device flow (RFC 8628), if supported
+    #[serde(deserialize_with = "deserialize_trimmed_option")]
+    pub device_authorization_url: Option<String>,
+    /// Overall timeout for each HTTP request, in seconds when serialized (default: none)
+    #[serde(with = "optional_duration_secs")]
+    pub request_timeout: Option<Duration>,
+    /// Timeout for establishing the connection, in seconds when serialized (default: none)
+    #[serde(with = "optional_duration_secs")]
+    pub connect_timeout: Option<Duration>,
+    /// Connection pool and keep-alive settings of the HTTP client
+    pub http_tuning: HttpTuning,
+    /// SHA-256 hashes of pinned certificate public keys (SPKI) (default: none)
+    ///
+    /// When non-empty, TLS connections are only accepted if the certificate
+    /// chain passes normal validation and one of its certificates matches a
+    /// pin. Pins apply to the hosts of `token_url`, `par_endpoint` and
+    /// `device_authorization_url`; other hosts such as `api_key_info_url`
+    /// get normal validation only. Requires the `cert-pinning` feature.
+    pub cert_pins: Vec<[u8; 32]>,
+    /// How long before expiry tokens are treated as expired, in seconds when serialized (default: 5 minutes)
+    ///
+    /// Used by the refresh helpers such as `OAuthClient::refresh_and_store`.
+    #[serde(with = "duration_secs")]
+    pub expiry_buffer: Duration,
+    /// Fail code exchanges that don't return a refresh token (default: false)
+    ///
+    /// A missing refresh token means `offline_access` wasn't granted, e.g.
+    /// because an account policy stripped it, and the session can't be
+    /// refreshed. By default such token sets are accepted with a `tracing`
+    /// warning; check `TokenSet::is_refreshable` to tell whether a refresh
+    /// token was granted. Set to `true` to fail the exchange instead.
+    pub require_refresh_token: bool,
+    /// Random bytes in each generated CSRF state (default: 32, minimum: 16)
+    ///
+    /// The state is the base64url encoding of this many bytes from the
+    /// thread-local CSPRNG. Flows fail with `InvalidConfig` below the minimum.
+    pub state_entropy_bytes: usize,
+    /// Random bytes in each generated PKCE verifier (default: 32, range: 32-96)
+    ///
+    /// The verifier is the base64url encoding of this many bytes, from 43
+    /// characters at 32 bytes up to the RFC 7636 maximum of 128 at 96 bytes.
+    /// Flows fail with `InvalidConfig` outside the range.
+    pub pkce_verifier_bytes: usize,
+    /// Send an OIDC `nonce` with each flow and check it in the id_token (default: false)
+    ///
+    /// Protects against id_token replay for apps that rely on the id_token.
+    /// Off by default to match the Codex CLI's authorization requests.
+    pub oidc_nonce: bool,
+    /// User-Agent sent with every HTTP request (default: "openai-auth/<version>")
+    ///
+    /// Set to `None` to use reqwest's default User-Agent.
+    pub user_agent: Option<String>,
+    /// Grant type for the API key token exchange
+    /// (default: "urn:ietf:params:oauth:grant-type:token-exchange")
+    pub token_exchange_grant_type: String,
+    /// Subject token type for the API key token exchange
+    /// (default: "urn:ietf:params:oauth:token-type:id_token")
+    pub subject_token_type: String,
+    /// User info endpoint used by `describe_api_key`
+    /// (default: "https://api.openai.com/v1/me")
+    #[serde(deserialize_with = "deserialize_trimmed")]
+    pub api_key_info_url: String,
 }
 
 impl Default for OAuthConfig {
+    /// The `Provider::OpenAiCodex` preset
     fn default() -> Self {
+        Self::for_provider(Provider::OpenAiCodex)
+    }
+}
+
+impl OAuthConfig {
+    /// Create a config for the given provider preset
+    ///
+    /// The preset sets the client ID, endpoints, scopes and extra
+    /// authorization parameters; all other fields get their defaults.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use openai_auth::{OAuthConfig, Provider, ProviderPreset};
+    ///
+    /// let openai = OAuthConfig::for_provider(Provider::OpenAiCodex);
+    /// assert_eq!(openai.client_id, OAuthConfig::default().client_id);
+    ///
+    /// let custom = OAuthConfig::for_provider(Provider::Custom(ProviderPreset {
+    ///     client_id: "my-client".to_string(),
+    ///     auth_url: "https://idp.example.com/authorize".to_string(),
+    ///     token_url: "https://idp.example.com/token".to_string(),
+    ///     scopes: vec!["openid".to_string(), "offline_access".to_string()],
+    ///     authorization_params: Vec::new(),
+    /// }));
+    /// ```
+    pub fn for_provider(provider: Provider) -> Self {
+        let preset = provider.preset();
         Self {
-            client_id: "app_EMoamEEZ73f0CkXaXp7hrann".to_string(),
-            auth_url: "https://auth.openai.com/oauth/authorize".to_string(),
-            token_url: "https://auth.openai.com/oauth/token".to_string(),
+            client_id: preset.client_id,
+            auth_url: preset.auth_url,
+            token_url: preset.token_url,
+            scopes: preset.scopes,
+            extra_authorization_params: preset.authorization_params,
             redirect_uri: "http://localhost:1455/auth/callback".to_string(),
+            redirect_ports: Vec::new(),
+            extra_headers: Vec::new(),
+            extra_token_params: Vec::new(),
+            par_endpoint: None,
+            device_authorization_url: None,
+            request_timeout: None,
+            connect_timeout: None,
+            http_tuning: HttpTuning::default(),
+            cert_pins: Vec::new(),
+            expiry_buffer: DEFAULT_EXPIRY_BUFFER,
+            require_refresh_token: false,
+            state_entropy_bytes: DEFAULT_STATE_ENTROPY_BYTES,
+            pkce_verifier_bytes: DEFAULT_PKCE_VERIFIER_BYTES,
+            oidc_nonce: false,
+            user_agent: Some(DEFAULT_USER_AGENT.to_string()),
+            token_exchange_grant_type: "urn:ietf:params:oauth:grant-type:token-exchange"
+                .to_string(),
+            subject_token_type: "urn:ietf:params:oauth:token-type:id_token".to_string(),
+            api_key_info_url: "https://api.openai.com/v1/me".to_string(),
         }
     }
-}
 
-impl OAuthConfig {
     /// Create a new config builder
     pub fn builder() -> OAuthConfigBuilder {
         OAuthConfigBuilder::default()
     }
+
+    /// Load a config from a TOML or JSON file
+    ///
+    /// Files with a `.toml` extension are parsed as TOML, anything else as
+    /// JSON. All fields are optional; missing fields fall back to the
+    /// defaults. Surrounding whitespace is trimmed from the URLs, which are
+    /// then validated.
+    ///
+    /// **Note:** This requires the `config-file` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or parsed, or if one of the
+    /// URLs is invalid
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use openai_auth::OAuthConfig;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = OAuthConfig::from_file("oauth.toml")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "config-file")]
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            OpenAIAuthError::InvalidConfig(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+
+        let config: OAuthConfig = if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&contents).map_err(|e| {
+                OpenAIAuthError::InvalidConfig(format!("Failed to parse {}: {}", path.display(), e))
+            })?
+        } else {
+            serde_json::from_str(&contents)?
+        };
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// The redirect URI registered with OpenAI for the loopback callback
+    pub fn callback_url(&self) -> &str {
+        &self.redirect_uri
+    }
+
+    /// The path portion of the redirect URI (e.g. `/auth/callback`)
+    ///
+    /// The callback server started by `OAuthClient::login` answers on this path.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` if the redirect URI isn't a valid URL
+    pub fn callback_path(&self) -> Result<String> {
+        let url = Url::parse(&self.redirect_uri).map_err(|e| {
+            OpenAIAuthError::InvalidConfig(format!(
+                "Invalid redirect_uri {:?}: {}",
+                self.redirect_uri, e
+            ))
+        })?;
+        Ok(url.path().to_string())
+    }
+
+    /// Check that the configured endpoint and redirect URLs are valid
+    ///
+    /// Besides parsing each URL, this catches common copy-paste mistakes:
+    /// surrounding whitespace and endpoints that aren't http(s) URLs. A
+    /// trailing slash on well-known OAuth endpoint paths such as
+    /// `/oauth/token/`, which servers typically answer with a 404, is logged
+    /// as a warning, since some deployments do serve those paths.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConfig` naming the first field that isn't a valid URL,
+    /// if `state_entropy_bytes` is below the minimum, or if
+    /// `pkce_verifier_bytes` is out of range
+    pub fn validate(&self) -> Result<()> {
+        validate_state_entropy(self.state_entropy_bytes)?;
+        validate_pkce_verifier_bytes(self.pkce_verifier_bytes)?;
+
+        let optional = [
+            ("par_endpoint", &self.par_endpoint),
+            ("device_authorization_url", &self.device_authorization_url),
+        ];
+        for (name, value) in [
+            ("auth_url", &self.auth_url),
+            ("token_url", &self.token_url),
+            ("redirect_uri", &self.redirect_uri),
+            ("api_key_info_url", &self.api_key_info_url),
+        ]
+        .into_iter()
+        .chain(
+            optional
+                .into_iter()
+                .filter_map(|(name, value)| value.as_ref().map(|value| (name, value))),
+        ) {
+            let invalid = |reason: String| {
+                OpenAIAuthError::InvalidConfig(format!("Invalid {} {:?}: {}", name, value, reason))
+            };
+            if value.trim() != value {
+                return Err(invalid("leading or trailing whitespace".to_string()));
+            }
+            let url = Url::parse(value).map_err(|e| invalid(e.to_string()))?;
+
+            // Custom schemes are allowed for the redirect URI only
+            if name == "redirect_uri" {
+                continue;
+            }
+            if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+                return Err(invalid("expected an http(s) URL".to_string()));
+            }
+            let path = url.path().trim_end_matches('/');
+            if url.path() != path && KNOWN_ENDPOINT_PATHS.iter().any(|p| path.ends_with(p)) {
+                tracing::warn!(
+                    field = name,
+                    url = %value,
+                    "trailing slash after {}, which usually causes a 404",
+                    path
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Trim surrounding whitespace from the configured URLs
+    fn trim_urls(&mut self) {
+        for url in [
+            &mut self.auth_url,
+            &mut self.token_url,
+            &mut self.redirect_uri,
+            &mut self.api_key_info_url,
+        ]
+        .into_iter()
+        .chain(self.par_endpoint.as_mut())
+        .chain(self.device_authorization_url.as_mut())
+        {
+            let trimmed = url.trim();
+            if trimmed.len() != url.len() {
+                *url = trimmed.to_string();
+            }
+        }
+    }
 }
 
+/// Endpoint paths that never end with a slash
+///
+/// `OAuthConfig::validate` warns about these with a trailing slash, a common
+/// copy-paste mistake; other paths are left alone.
+const KNOWN_ENDPOINT_PATHS: [&str; 5] = [
+    "/oauth/token",
+    "/oauth/authorize",
+    "/oauth/par",
+    "/oauth/device/code",
+    "/v1/me",
+];
+
 /// Builder for OAuthConfig
 #[derive(Debug, Clone, Default)]
 pub struct OAuthConfigBuilder {
@@ -97,6 +963,27 @@ pub struct OAuthConfigBuilder {
     auth_url: Option<String>,
     token_url: Option<String>,
     redirect_uri: Option<String>,
+    provider: Option<Provider>,
+    scopes: Option<Vec<String>>,
+    extra_authorization_params: Vec<(String, String)>,
+    redirect_ports: Vec<u16>,
+    extra_headers: Vec<(String, String)>,
+    extra_token_params: Vec<(String, String)>,
+    par_endpoint: Option<String>,
+    device_authorization_url: Option<String>,
+    request_timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    http_tuning: Option<HttpTuning>,
+    cert_pins: Vec<[u8; 32]>,
+    expiry_buffer: Option<Duration>,
+    require_refresh_token: Option<bool>,
+    state_entropy_bytes: Option<usize>,
+    pkce_verifier_bytes: Option<usize>,
+    oidc_nonce: Option<bool>,
+    user_agent: Option<Option<String>>,
+    token_exchange_grant_type: Option<String>,
+    subject_token_type: Option<String>,
+    api_key_info_url: Option<String>,
 }
 
 impl OAuthConfigBuilder {
@@ -119,27 +1006,304 @@ impl OAuthConfigBuilder {
     }
 
     /// Set the redirect URI
+    ///
+    /// Any scheme is accepted, including custom app schemes (`myapp://auth/callback`).
     pub fn redirect_uri(mut self, redirect_uri: impl Into<String>) -> Self {
         self.redirect_uri = Some(redirect_uri.into());
         self
     }
 
+    /// Start from a provider preset instead of the OpenAI one
+    ///
+    /// Values set explicitly on the builder override the preset's.
+    pub fn provider(mut self, provider: Provider) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// Set the scopes requested by the authorization URL, replacing the preset's
+    pub fn scopes(mut self, scopes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.scopes = Some(scopes.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Add an extra query parameter to the authorization URL
+    ///
+    /// Can be called multiple times. The parameters are sent in addition to
+    /// those of the provider preset.
+    pub fn authorization_param(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.extra_authorization_params
+            .push((name.into(), value.into()));
+        self
+    }
+
     /// Set the redirect URI with a custom port
     pub fn redirect_port(mut self, port: u16) -> Self {
         self.redirect_uri = Some(format!("http://localhost:{}/auth/callback", port));
         self
     }
 
+    /// Set the ports to try in order for the callback server
+    ///
+    /// The first free port is used and the redirect URI adjusted to match.
+    pub fn redirect_ports(mut self, ports: impl IntoIterator<Item = u16>) -> Self {
+        self.redirect_ports = ports.into_iter().collect();
+        self
+    }
+
+    /// Add an extra header to send with every token endpoint request
+    ///
+    /// Can be called multiple times to add several headers.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra_headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Add an extra form parameter to send with every token endpoint request
+    ///
+    /// Can be called multiple times to add several parameters.
+    pub fn token_param(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra_token_params.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set the pushed authorization request endpoint URL, enabling `start_flow_par`
+    pub fn par_endpoint(mut self, url: impl Into<String>) -> Self {
+        self.par_endpoint = Some(url.into());
+        self
+    }
+
+    /// Set the device authorization endpoint URL, enabling the device flow
+    pub fn device_authorization_url(mut self, url: impl Into<String>) -> Self {
+        self.device_authorization_url = Some(url.into());
+        self
+    }
+
+    /// Set the overall timeout for each HTTP request
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the timeout for establishing a connection
+    ///
+    /// Use a short connect timeout to fail fast on DNS or connection problems
+    /// while allowing a longer `request_timeout` for slow responses.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the connection pool and keep-alive settings of the HTTP client
+    pub fn http_tuning(mut self, tuning: HttpTuning) -> Self {
+        self.http_tuning = Some(tuning);
+        self
+    }
+
+    /// Pin a certificate public key by the SHA-256 hash of its SPKI
+    ///
+    /// Can be called multiple times to allow several keys (e.g. for rotation).
+    /// Requires the `cert-pinning` feature.
+    pub fn cert_pin(mut self, spki_sha256: [u8; 32]) -> Self {
+        self.cert_pins.push(spki_sha256);
+        self
+    }
+
+    /// Set how long before expiry tokens should be refreshed (default: 5 minutes)
+    pub fn expiry_buffer(mut self, buffer: Duration) -> Self {
+        self.expiry_buffer = Some(buffer);
+        self
+    }
+
+    /// Set whether code exchanges without a refresh token fail (default: false)
+    pub fn require_refresh_token(mut self, require: bool) -> Self {
+        self.require_refresh_token = Some(require);
+        self
+    }
+
+    /// Set the number of random bytes in each generated state (default: 32, minimum: 16)
+    pub fn state_entropy_bytes(mut self, bytes: usize) -> Self {
+        self.state_entropy_bytes = Some(bytes);
+        self
+    }
+
+    /// Set the number of random bytes in each generated PKCE verifier (default: 32, range: 32-96)
+    pub fn pkce_verifier_bytes(mut self, bytes: usize) -> Self {
+        self.pkce_verifier_bytes = Some(bytes);
+        self
+    }
+
+    /// Set whether flows send and check an OIDC nonce (default: false)
+    pub fn oidc_nonce(mut self, enabled: bool) -> Self {
+        self.oidc_nonce = Some(enabled);
+        self
+    }
+
+    /// Set the User-Agent sent with every HTTP request
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(Some(user_agent.into()));
+        self
+    }
+
+    /// Use reqwest's default User-Agent instead of `openai-auth/<version>`
+    pub fn default_user_agent(mut self) -> Self {
+        self.user_agent = Some(None);
+        self
+    }
+
+    /// Set the grant type URN used for the API key token exchange
+    pub fn token_exchange_grant_type(mut self, grant_type: impl Into<String>) -> Self {
+        self.token_exchange_grant_type = Some(grant_type.into());
+        self
+    }
+
+    /// Set the subject token type URN used for the API key token exchange
+    pub fn subject_token_type(mut self, token_type: impl Into<String>) -> Self {
+        self.subject_token_type = Some(token_type.into());
+        self
+    }
+
+    /// Set the user info endpoint used by `describe_api_key`
+    pub fn api_key_info_url(mut self, url: impl Into<String>) -> Self {
+        self.api_key_info_url = Some(url.into());
+        self
+    }
+
     /// Build the OAuthConfig
+    ///
+    /// Surrounding whitespace is trimmed from the URLs, e.g. after pasting
+    /// them from a browser or reading them from the environment.
     pub fn build(self) -> OAuthConfig {
-        let defaults = OAuthConfig::default();
-        OAuthConfig {
+        let defaults = OAuthConfig::for_provider(self.provider.unwrap_or_default());
+        let mut config = OAuthConfig {
             client_id: self.client_id.unwrap_or(defaults.client_id),
             auth_url: self.auth_url.unwrap_or(defaults.auth_url),
             token_url: self.token_url.unwrap_or(defaults.token_url),
             redirect_uri: self.redirect_uri.unwrap_or(defaults.redirect_uri),
+            scopes: self.scopes.unwrap_or(defaults.scopes),
+            extra_authorization_params: defaults
+                .extra_authorization_params
+                .into_iter()
+                .chain(self.extra_authorization_params)
+                .collect(),
+            redirect_ports: self.redirect_ports,
+            extra_headers: self.extra_headers,
+            extra_token_params: self.extra_token_params,
+            par_endpoint: self.par_endpoint,
+            device_authorization_url: self.device_authorization_url,
+            request_timeout: self.request_timeout,
+            connect_timeout: self.connect_timeout,
+            http_tuning: self.http_tuning.unwrap_or(defaults.http_tuning),
+            cert_pins: self.cert_pins,
+            expiry_buffer: self.expiry_buffer.unwrap_or(defaults.expiry_buffer),
+            require_refresh_token: self
+                .require_refresh_token
+                .unwrap_or(defaults.require_refresh_token),
+            state_entropy_bytes: self
+                .state_entropy_bytes
+                .unwrap_or(defaults.state_entropy_bytes),
+            pkce_verifier_bytes: self
+                .pkce_verifier_bytes
+                .unwrap_or(defaults.pkce_verifier_bytes),
+            oidc_nonce: self.oidc_nonce.unwrap_or(defaults.oidc_nonce),
+            user_agent: self.user_agent.unwrap_or(defaults.user_agent),
+            token_exchange_grant_type: self
+                .token_exchange_grant_type
+                .unwrap_or(defaults.token_exchange_grant_type),
+            subject_token_type: self
+                .subject_token_type
+                .unwrap_or(defaults.subject_token_type),
+            api_key_info_url: self.api_key_info_url.unwrap_or(defaults.api_key_info_url),
+        };
+        config.trim_urls();
+        config
+    }
+}
+
+/// Connection pool and keep-alive settings of the HTTP client
+///
+/// Services refreshing many accounts keep connections to the token endpoint
+/// open between requests. Raise `pool_idle_timeout` and enable
+/// `tcp_keepalive` to reuse them longer and avoid repeated TLS handshakes;
+/// lower `pool_max_idle_per_host` to bound the number of idle sockets.
+///
+/// Durations are whole seconds when serialized.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use openai_auth::{HttpTuning, OAuthConfig};
+///
+/// let config = OAuthConfig::builder()
+///     .http_tuning(HttpTuning {
+///         pool_idle_timeout: Some(Duration::from_secs(300)),
+///         pool_max_idle_per_host: Some(16),
+///         tcp_keepalive: Some(Duration::from_secs(60)),
+///     })
+///     .build();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpTuning {
+    /// How long idle connections are kept in the pool (default: 90 seconds, `None`: forever)
+    #[serde(with = "optional_duration_secs")]
+    pub pool_idle_timeout: Option<Duration>,
+    /// Maximum idle connections kept per host (default: `None`, unlimited)
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Interval of TCP keep-alive probes (default: `None`, disabled)
+    #[serde(with = "optional_duration_secs")]
+    pub tcp_keepalive: Option<Duration>,
+}
+
+impl Default for HttpTuning {
+    fn default() -> Self {
+        Self {
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            pool_max_idle_per_host: None,
+            tcp_keepalive: None,
+        }
+    }
+}
+
+/// Serialize a `Duration` as whole seconds
+mod duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(value.as_secs())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_secs(u64::deserialize(deserializer)?))
+    }
+}
+
+/// Serialize an optional `Duration` as whole seconds
+mod optional_duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(duration) => serializer.serialize_some(&duration.as_secs()),
+            None => serializer.serialize_none(),
         }
     }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_secs))
+    }
 }
 
 /// Token response from OAuth server
@@ -148,44 +1312,827 @@ pub(crate) struct TokenResponse {
     pub access_token: String,
     pub id_token: Option<String>,
     pub refresh_token: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_expires_in")]
     pub expires_in: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_expires_in")]
+    pub refresh_expires_in: Option<u64>,
+}
+
+/// Trim surrounding whitespace from a deserialized URL
+///
+/// Config files and environment values often carry a stray newline or space.
+fn deserialize_trimmed<'de, D>(deserializer: D) -> std::result::Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    String::deserialize(deserializer).map(|s| s.trim().to_string())
+}
+
+/// Trim surrounding whitespace from an optional deserialized URL
+fn deserialize_trimmed_option<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer).map(|s| s.map(|s| s.trim().to_string()))
+}
+
+/// Accept `expires_in` as either a number or a numeric string
+///
+/// Some non-compliant servers and proxies send `"expires_in": "3600"`.
+fn deserialize_expires_in<'de, D>(deserializer: D) -> std::result::Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString {
+        Number(u64),
+        String(String),
+    }
+
+    match Option::<NumberOrString>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(NumberOrString::Number(n)) => Ok(Some(n)),
+        Some(NumberOrString::String(s)) => s
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| serde::de::Error::custom(format!("invalid expires_in: {:?}", s))),
+    }
 }
 
 impl From<TokenResponse> for TokenSet {
     fn from(response: TokenResponse) -> Self {
-        let expires_at = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-            + response.expires_in.unwrap_or(3600);
+        TokenSet::from_response(response, SystemTime::now())
+    }
+}
+
+impl TokenSet {
+    /// Build a `TokenSet` from a token response received at `now`
+    pub(crate) fn from_response(response: TokenResponse, now: SystemTime) -> Self {
+        let expires_at = unix_secs(now).saturating_add(response.expires_in.unwrap_or(3600));
 
         TokenSet {
             access_token: response.access_token,
             id_token: response.id_token,
             refresh_token: response.refresh_token.unwrap_or_default(),
             expires_at,
+            issued_at: Some(unix_secs(now)),
+            refresh_expires_at: response
+                .refresh_expires_in
+                .map(|secs| unix_secs(now).saturating_add(secs)),
             api_key: None,
         }
     }
 }
 
-/// Generate a random state string for CSRF protection
-pub(crate) fn generate_random_state() -> String {
+/// Seconds since the Unix epoch, or 0 for times before it
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Parameters of the authorization request
+///
+/// Sent as query parameters of the authorization URL, or as the body of a
+/// pushed authorization request.
+pub(crate) fn authorization_params<'a>(
+    config: &'a OAuthConfig,
+    scope: &'a str,
+    pkce_challenge: &'a str,
+    state: &'a str,
+) -> Vec<(&'a str, &'a str)> {
+    let mut params = vec![
+        ("response_type", "code"),
+        ("client_id", &*config.client_id),
+        ("redirect_uri", &*config.redirect_uri),
+        ("scope", scope),
+        ("code_challenge", pkce_challenge),
+        ("code_challenge_method", "S256"),
+        ("state", state),
+    ];
+    params.extend(
+        config
+            .extra_authorization_params
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str())),
+    );
+    params
+}
+
+/// Form parameters of the API key token exchange (RFC 8693)
+///
+/// `actor` adds the `actor_token` and `actor_token_type` of a party acting
+/// on behalf of the subject.
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+pub(crate) fn token_exchange_params<'a>(
+    config: &'a OAuthConfig,
+    subject_token: &'a str,
+    actor: Option<(&'a str, &'a str)>,
+) -> Vec<(&'a str, &'a str)> {
+    let mut params = vec![
+        ("grant_type", config.token_exchange_grant_type.as_str()),
+        ("client_id", config.client_id.as_str()),
+        ("requested_token", "openai-api-key"),
+        ("subject_token", subject_token),
+        ("subject_token_type", config.subject_token_type.as_str()),
+    ];
+    if let Some((actor_token, actor_token_type)) = actor {
+        params.push(("actor_token", actor_token));
+        params.push(("actor_token_type", actor_token_type));
+    }
+    params
+}
+
+/// Form parameters for a token endpoint request
+///
+/// Starts from the parameters of the grant and merges in the configured
+/// `extra_token_params`, which replace parameters of the same name.
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+pub(crate) fn token_form(
+    params: &[(&str, &str)],
+    extra_params: &[(String, String)],
+) -> Vec<(String, String)> {
+    let mut form: Vec<(String, String)> = params
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    for (name, value) in extra_params {
+        match form.iter_mut().find(|(existing, _)| existing == name) {
+            Some(param) => param.1 = value.clone(),
+            None => form.push((name.clone(), value.clone())),
+        }
+    }
+    form
+}
+
+/// Response from a pushed authorization request endpoint
+#[cfg(any(feature = "async", feature = "blocking"))]
+#[derive(Debug, Deserialize)]
+pub(crate) struct ParResponse {
+    pub request_uri: String,
+}
+
+/// Deserialize a JSON response body
+///
+/// If the body isn't JSON at all (e.g. an HTML page from a proxy or captive
+/// portal), this returns the error built by `error` from a message including
+/// a snippet of the body instead of a raw serde error, so each caller can
+/// report it as its own kind of failure.
+#[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+pub(crate) fn parse_json<T: DeserializeOwned>(
+    body: &str,
+    error: impl FnOnce(String) -> OpenAIAuthError,
+) -> Result<T> {
+    serde_json::from_str(body).map_err(|e| {
+        if e.is_syntax() || e.is_eof() {
+            let snippet: String = body.trim().chars().take(200).collect();
+            error(format!("unexpected non-JSON response: {}", snippet))
+        } else {
+            e.into()
+        }
+    })
+}
+
+/// Clean up an authorization code pasted by a user
+///
+/// Trims whitespace, strips a leading `?`/`#`, extracts the exact `code`
+/// parameter if a query string or URL was pasted, drops any trailing
+/// fragment, and percent-decodes the code if it looks encoded.
+pub(crate) fn sanitize_code(code: &str) -> String {
+    let mut code = code.trim().trim_start_matches(['?', '#']);
+
+    if code.contains('=') {
+        let query = code.split_once('?').map_or(code, |(_, query)| query);
+        let query = query.split_once('#').map_or(query, |(query, _)| query);
+        if let Some((_, value)) =
+            url::form_urlencoded::parse(query.as_bytes()).find(|(key, _)| key == "code")
+        {
+            return value.into_owned();
+        }
+    }
+    if let Some((before, _)) = code.split_once('#') {
+        code = before;
+    }
+
+    let looks_encoded = code
+        .as_bytes()
+        .windows(3)
+        .any(|w| w[0] == b'%' && w[1].is_ascii_hexdigit() && w[2].is_ascii_hexdigit());
+    if looks_encoded {
+        percent_encoding::percent_decode_str(code)
+            .decode_utf8_lossy()
+            .into_owned()
+    } else {
+        code.to_string()
+    }
+}
+
+/// Generate a CSRF state with `config.state_entropy_bytes` of randomness
+pub(crate) fn generate_random_state(config: &OAuthConfig) -> Result<String> {
     use base64::{Engine as _, engine::general_purpose};
     use rand::Rng;
 
-    let random_bytes: Vec<u8> = (0..32).map(|_| rand::thread_rng().r#gen()).collect();
-    general_purpose::URL_SAFE_NO_PAD.encode(&random_bytes)
+    validate_state_entropy(config.state_entropy_bytes)?;
+    let random_bytes: Vec<u8> = (0..config.state_entropy_bytes)
+        .map(|_| rand::thread_rng().r#gen())
+        .collect();
+    Ok(general_purpose::URL_SAFE_NO_PAD.encode(&random_bytes))
 }
 
-pub(crate) fn generate_pkce_pair() -> (String, String) {
+fn validate_state_entropy(bytes: usize) -> Result<()> {
+    if bytes < MIN_STATE_ENTROPY_BYTES {
+        return Err(OpenAIAuthError::InvalidConfig(format!(
+            "state_entropy_bytes must be at least {}, got {}",
+            MIN_STATE_ENTROPY_BYTES, bytes
+        )));
+    }
+    Ok(())
+}
+
+/// Generate a PKCE (challenge, verifier) pair with `config.pkce_verifier_bytes` of randomness
+pub(crate) fn generate_pkce_pair(config: &OAuthConfig) -> Result<(String, String)> {
     use base64::{Engine as _, engine::general_purpose};
     use rand::RngCore;
 
-    let mut bytes = [0u8; 32];
+    validate_pkce_verifier_bytes(config.pkce_verifier_bytes)?;
+    let mut bytes = vec![0u8; config.pkce_verifier_bytes];
     rand::thread_rng().fill_bytes(&mut bytes);
     let verifier = general_purpose::URL_SAFE_NO_PAD.encode(bytes);
-    let digest = Sha256::digest(verifier.as_bytes());
-    let challenge = general_purpose::URL_SAFE_NO_PAD.encode(digest);
-    (challenge, verifier)
+    Ok((pkce_challenge(&verifier), verifier))
+}
+
+fn validate_pkce_verifier_bytes(bytes: usize) -> Result<()> {
+    if !PKCE_VERIFIER_BYTES.contains(&bytes) {
+        return Err(OpenAIAuthError::InvalidConfig(format!(
+            "pkce_verifier_bytes must be {} to {}, got {}",
+            PKCE_VERIFIER_BYTES.start(),
+            PKCE_VERIFIER_BYTES.end(),
+            bytes
+        )));
+    }
+    Ok(())
+}
+
+/// Check that a PKCE verifier is valid per RFC 7636
+///
+/// The verifier must be 43 to 128 characters from the unreserved set
+/// `[A-Z] / [a-z] / [0-9] / "-" / "." / "_" / "~"`.
+pub(crate) fn validate_verifier(verifier: &str) -> Result<()> {
+    if !(43..=128).contains(&verifier.len()) {
+        return Err(OpenAIAuthError::InvalidConfig(format!(
+            "PKCE verifier must be 43-128 characters, got {}",
+            verifier.len()
+        )));
+    }
+    if let Some(c) = verifier
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~')))
+    {
+        return Err(OpenAIAuthError::InvalidConfig(format!(
+            "PKCE verifier contains invalid character {:?}",
+            c
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_set(suffix: &str, optional: bool) -> TokenSet {
+        TokenSet {
+            access_token: format!("access-{}", suffix),
+            id_token: optional.then(|| format!("id-{}", suffix)),
+            refresh_token: if optional {
+                format!("refresh-{}", suffix)
+            } else {
+                String::new()
+            },
+            expires_at: if optional { 1 } else { 2 },
+            issued_at: None,
+            refresh_expires_at: None,
+            api_key: optional.then(|| format!("key-{}", suffix)),
+        }
+    }
+
+    #[test]
+    fn test_time_until_refresh_saturates() {
+        let tokens = TokenSet {
+            expires_at: 1000,
+            refresh_expires_at: None,
+            ..token_set("a", true)
+        };
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let buffer = Duration::from_secs(300);
+
+        assert_eq!(
+            tokens.time_until_refresh_at(at(100), buffer),
+            Duration::from_secs(600)
+        );
+        assert_eq!(
+            tokens.time_until_refresh_at(at(700), buffer),
+            Duration::ZERO
+        );
+        assert_eq!(
+            tokens.time_until_refresh_at(at(900), buffer),
+            Duration::ZERO
+        );
+        assert_eq!(
+            tokens.time_until_refresh_at(at(2000), buffer),
+            Duration::ZERO
+        );
+        assert_eq!(
+            tokens.time_until_refresh_at(at(900), Duration::ZERO),
+            Duration::from_secs(100)
+        );
+        assert_eq!(tokens.time_until_refresh(buffer), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_lifetime_below_buffer_uses_issue_time() {
+        let tokens = TokenSet::builder("a", 1060).issued_at(1000).build();
+        assert!(tokens.lifetime_below_buffer(Duration::from_secs(300)));
+        assert!(tokens.lifetime_below_buffer(Duration::from_secs(60)));
+        assert!(!tokens.lifetime_below_buffer(Duration::from_secs(30)));
+
+        // Without an issue time the lifetime is unknown
+        let tokens = TokenSet::builder("a", 1060).build();
+        assert!(!tokens.lifetime_below_buffer(Duration::from_secs(300)));
+    }
+
+    #[test]
+    fn test_state_entropy_bytes() {
+        let config = OAuthConfig::default();
+        assert_eq!(generate_random_state(&config).unwrap().len(), 43);
+
+        let config = OAuthConfig::builder().state_entropy_bytes(16).build();
+        assert_eq!(generate_random_state(&config).unwrap().len(), 22);
+
+        let config = OAuthConfig::builder().state_entropy_bytes(15).build();
+        assert!(matches!(
+            generate_random_state(&config),
+            Err(OpenAIAuthError::InvalidConfig(_))
+        ));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_catches_copy_paste_mistakes() {
+        let config = OAuthConfig::builder()
+            .token_url(" https://auth.openai.com/oauth/token\n")
+            .build();
+        assert_eq!(config.token_url, "https://auth.openai.com/oauth/token");
+        config.validate().unwrap();
+
+        let invalid = |json: &str| {
+            let config: OAuthConfig = serde_json::from_str(json).unwrap();
+            match config.validate() {
+                Err(OpenAIAuthError::InvalidConfig(e)) => e,
+                other => panic!("{:?}", other),
+            }
+        };
+        assert!(invalid(r#"{"token_url": "auth.openai.com:443/oauth/token"}"#).contains("http(s)"));
+
+        // Deserialized URLs are trimmed too
+        let config: OAuthConfig = serde_json::from_str(
+            r#"{"auth_url": "https://auth.openai.com/oauth/authorize ", "par_endpoint": "\thttps://auth.openai.com/oauth/par"}"#,
+        )
+        .unwrap();
+        assert_eq!(config.auth_url, "https://auth.openai.com/oauth/authorize");
+        assert_eq!(
+            config.par_endpoint.as_deref(),
+            Some("https://auth.openai.com/oauth/par")
+        );
+        config.validate().unwrap();
+
+        // Untrimmed URLs set directly are still rejected
+        let config = OAuthConfig {
+            auth_url: "https://auth.openai.com/oauth/authorize ".to_string(),
+            ..OAuthConfig::default()
+        };
+        assert!(matches!(
+            config.validate(),
+            Err(OpenAIAuthError::InvalidConfig(e)) if e.contains("whitespace")
+        ));
+
+        // A trailing slash on a known endpoint only warns
+        let config: OAuthConfig =
+            serde_json::from_str(r#"{"token_url": "https://auth.openai.com/oauth/token/"}"#)
+                .unwrap();
+        config.validate().unwrap();
+
+        // Other paths and custom-scheme redirects are left alone
+        let config: OAuthConfig = serde_json::from_str(
+            r#"{"token_url": "https://idp.example.com/tenant/", "redirect_uri": "myapp://cb"}"#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_with_auth_host_keeps_parameters() {
+        let flow = OAuthFlow::from_parts(
+            "https://auth.openai.com/oauth/authorize?code_challenge=abc&state=s1",
+            "a".repeat(43),
+            "s1",
+        );
+        let regional = flow.with_auth_host("auth.eu.example.com").unwrap();
+        assert_eq!(
+            regional.authorization_url,
+            "https://auth.eu.example.com/oauth/authorize?code_challenge=abc&state=s1"
+        );
+        assert_eq!(regional.pkce_verifier, flow.pkce_verifier);
+        assert_eq!(regional.state, flow.state);
+
+        for invalid in ["", "evil.com/path", "host name"] {
+            assert!(matches!(
+                flow.with_auth_host(invalid),
+                Err(OpenAIAuthError::InvalidConfig(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_validate_against_detects_corruption() {
+        let config = OAuthConfig::default();
+        let flow = crate::AuthorizationUrlBuilder::new(&config)
+            .param("audience", "api")
+            .build()
+            .unwrap();
+        flow.validate_against(&config).unwrap();
+        flow.with_auth_host("auth.eu.example.com")
+            .unwrap()
+            .validate_against(&config)
+            .unwrap();
+
+        let other = OAuthConfig::builder().client_id("other").build();
+        assert!(matches!(
+            flow.validate_against(&other),
+            Err(OpenAIAuthError::InvalidConfig(e)) if e.contains("client_id")
+        ));
+
+        let corrupt = |url: String| OAuthFlow {
+            authorization_url: url,
+            ..flow.clone()
+        };
+        let repeated = corrupt(format!("{}&state=other", flow.authorization_url));
+        assert!(repeated.validate_against(&config).is_err());
+        let plain = corrupt(
+            flow.authorization_url
+                .replace("code_challenge_method=S256", "code_challenge_method=plain"),
+        );
+        assert!(plain.validate_against(&config).is_err());
+
+        let mismatched = OAuthFlow {
+            pkce_verifier: "b".repeat(43),
+            ..flow.clone()
+        };
+        assert!(mismatched.validate_against(&config).is_err());
+    }
+
+    #[test]
+    fn test_audit_summary_excludes_secrets() {
+        let config = OAuthConfig::default();
+        let mut url = Url::parse(&config.auth_url).unwrap();
+        let scope = config.scopes.join(" ");
+        url.query_pairs_mut().extend_pairs(authorization_params(
+            &config,
+            &scope,
+            "challenge-value",
+            "state-value",
+        ));
+        let flow = OAuthFlow::from_parts(url.as_str(), "verifier-value", "state-value");
+
+        let summary = flow.audit_summary();
+        assert_eq!(
+            summary.client_id.as_deref(),
+            Some(config.client_id.as_str())
+        );
+        assert_eq!(
+            summary.redirect_uri.as_deref(),
+            Some(config.redirect_uri.as_str())
+        );
+        assert!(summary.scopes.contains(&"offline_access".to_string()));
+        assert_eq!(summary.code_challenge_method.as_deref(), Some("S256"));
+
+        let logged = format!("{:?}", summary);
+        assert!(!logged.contains("verifier-value"));
+        assert!(!logged.contains("state-value"));
+    }
+
+    #[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+    #[test]
+    fn test_token_form_merges_extra_params() {
+        let extra = [
+            ("client_id".to_string(), "override".to_string()),
+            ("actor_token".to_string(), "actor".to_string()),
+        ];
+        let form = token_form(
+            &[("grant_type", "refresh_token"), ("client_id", "app")],
+            &extra,
+        );
+        assert_eq!(
+            form,
+            [
+                ("grant_type".to_string(), "refresh_token".to_string()),
+                ("client_id".to_string(), "override".to_string()),
+                ("actor_token".to_string(), "actor".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_callback_path() {
+        let config = OAuthConfig::builder()
+            .redirect_uri("http://localhost:8080/oauth/done?x=1")
+            .build();
+        assert_eq!(
+            config.callback_url(),
+            "http://localhost:8080/oauth/done?x=1"
+        );
+        assert_eq!(config.callback_path().unwrap(), "/oauth/done");
+        assert_eq!(
+            OAuthConfig::default().callback_path().unwrap(),
+            "/auth/callback"
+        );
+    }
+
+    #[test]
+    fn test_display_hides_secrets() {
+        let mut tokens = token_set("secret", true);
+        assert_eq!(
+            tokens.to_string(),
+            "TokenSet(expired, has_refresh, has_id_token, has_api_key)"
+        );
+
+        tokens.expires_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 3600 * 2
+            + 90;
+        tokens.api_key = None;
+        let summary = tokens.to_string();
+        assert!(
+            summary.starts_with("TokenSet(expires in 2h1m"),
+            "{}",
+            summary
+        );
+        assert!(!summary.contains("secret"));
+        assert!(!summary.contains("has_api_key"));
+    }
+
+    #[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+    #[test]
+    fn test_token_exchange_actor_params() {
+        let config = OAuthConfig::default();
+        let params = token_exchange_params(&config, "id-token", None);
+        assert!(!params.iter().any(|(name, _)| name.starts_with("actor")));
+
+        let params = token_exchange_params(&config, "id-token", Some(("svc", "urn:actor")));
+        assert_eq!(
+            &params[params.len() - 2..],
+            [("actor_token", "svc"), ("actor_token_type", "urn:actor")]
+        );
+        assert!(params.contains(&("subject_token", "id-token")));
+    }
+
+    #[test]
+    fn test_token_set_builder() {
+        let tokens = TokenSet::builder("access", 10).build();
+        assert_eq!(tokens.access_token, "access");
+        assert_eq!(tokens.expires_at, 10);
+        assert!(!tokens.is_refreshable());
+        assert_eq!((tokens.id_token, tokens.api_key), (None, None));
+
+        let tokens = TokenSet::builder("access", 10)
+            .refresh_token("refresh")
+            .id_token("id")
+            .api_key("key")
+            .refresh_expires_at(20)
+            .build();
+        assert_eq!(tokens.refresh_token, "refresh");
+        assert_eq!(tokens.id_token.as_deref(), Some("id"));
+        assert_eq!(tokens.api_key.as_deref(), Some("key"));
+        assert_eq!(tokens.refresh_expires_at, Some(20));
+    }
+
+    #[test]
+    fn test_masked_tokens_keep_only_ends() {
+        let mut tokens = token_set("secret", true);
+        tokens.access_token = "sk-proj-0123456789abcd".to_string();
+        tokens.refresh_token = "short".to_string();
+        assert_eq!(tokens.masked_access_token(), "sk-p...abcd");
+        assert_eq!(tokens.masked_refresh_token(), "***");
+        assert_eq!(tokens.masked_api_key().as_deref(), Some("***"));
+
+        tokens.api_key = Some("ключ-ключ-ключ-ключ".to_string());
+        assert_eq!(tokens.masked_api_key().as_deref(), Some("ключ...ключ"));
+        tokens.api_key = None;
+        assert_eq!(tokens.masked_api_key(), None);
+    }
+
+    #[test]
+    fn test_merge_preserves_omitted_fields() {
+        let mut tokens = token_set("old", true);
+        tokens.merge(token_set("new", false));
+
+        assert_eq!(tokens.access_token, "access-new");
+        assert_eq!(tokens.expires_at, 2);
+        assert_eq!(tokens.refresh_token, "refresh-old");
+        assert_eq!(tokens.id_token.as_deref(), Some("id-old"));
+        assert_eq!(tokens.api_key.as_deref(), Some("key-old"));
+    }
+
+    #[test]
+    fn test_merge_replaces_present_fields() {
+        let mut tokens = token_set("old", true);
+        tokens.merge(token_set("new", true));
+
+        assert_eq!(tokens.refresh_token, "refresh-new");
+        assert_eq!(tokens.id_token.as_deref(), Some("id-new"));
+        assert_eq!(tokens.api_key.as_deref(), Some("key-new"));
+    }
+
+    #[test]
+    fn test_huge_lifetimes_saturate() {
+        let response: TokenResponse = serde_json::from_str(
+            r#"{"access_token":"x","expires_in":"18446744073709551615","refresh_expires_in":18446744073709551615}"#,
+        )
+        .unwrap();
+        let tokens = TokenSet::from_response(response, SystemTime::now());
+        assert_eq!(tokens.expires_at, u64::MAX);
+        assert_eq!(tokens.refresh_expires_at, Some(u64::MAX));
+        assert!(!tokens.is_expired());
+        assert!(!tokens.refresh_is_expired());
+    }
+
+    #[test]
+    fn test_refresh_token_expiry() {
+        let response: TokenResponse = serde_json::from_str(
+            r#"{"access_token":"x","refresh_token":"r","refresh_expires_in":"259200"}"#,
+        )
+        .unwrap();
+        let mut tokens = TokenSet::from_response(response, SystemTime::now());
+        let remaining = tokens.refresh_expires_in().unwrap();
+        assert!(
+            remaining > Duration::from_secs(259_000) && remaining <= Duration::from_secs(259_200)
+        );
+        assert!(!tokens.refresh_is_expired());
+
+        // An injected clock decides expiry without waiting
+        let issued = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let tokens_at = TokenSet::from_response(
+            serde_json::from_str(
+                r#"{"access_token":"x","refresh_token":"r","refresh_expires_in":60}"#,
+            )
+            .unwrap(),
+            issued,
+        );
+        assert_eq!(
+            tokens_at.refresh_expires_in_at(issued + Duration::from_secs(20)),
+            Some(Duration::from_secs(40))
+        );
+        assert!(!tokens_at.refresh_is_expired_at(issued + Duration::from_secs(59)));
+        assert!(tokens_at.refresh_is_expired_at(issued + Duration::from_secs(60)));
+
+        // A rotated refresh token without a reported lifetime drops the old one
+        tokens.merge(token_set("new", true));
+        assert_eq!(tokens.refresh_expires_in(), None);
+        assert!(!tokens.refresh_is_expired());
+
+        tokens.refresh_expires_at = Some(1);
+        assert!(tokens.refresh_is_expired());
+        tokens.merge(token_set("newer", false));
+        assert_eq!(tokens.refresh_expires_at, Some(1));
+    }
+
+    #[test]
+    fn test_config_partial_json() {
+        let config: OAuthConfig =
+            serde_json::from_str(r#"{"redirect_uri": "http://localhost:8080/auth/callback"}"#)
+                .unwrap();
+        assert_eq!(config.redirect_uri, "http://localhost:8080/auth/callback");
+        assert_eq!(config.client_id, OAuthConfig::default().client_id);
+
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["client_id"], config.client_id);
+
+        let config: OAuthConfig = serde_json::from_str(r#"{"connect_timeout": 5}"#).unwrap();
+        assert_eq!(config.connect_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(config.request_timeout, None);
+        assert_eq!(config.expiry_buffer, Duration::from_secs(300));
+
+        let config: OAuthConfig = serde_json::from_str(r#"{"expiry_buffer": 30}"#).unwrap();
+        assert_eq!(config.expiry_buffer, Duration::from_secs(30));
+
+        let config: OAuthConfig =
+            serde_json::from_str(r#"{"http_tuning": {"tcp_keepalive": 60}}"#).unwrap();
+        assert_eq!(
+            config.http_tuning,
+            HttpTuning {
+                tcp_keepalive: Some(Duration::from_secs(60)),
+                ..HttpTuning::default()
+            }
+        );
+    }
+
+    #[cfg(feature = "config-file")]
+    #[test]
+    fn test_from_file_partial_toml() {
+        let path = std::env::temp_dir().join(format!("openai-auth-{}.toml", std::process::id()));
+        std::fs::write(&path, "client_id = \"my-client\"\n").unwrap();
+
+        let config = OAuthConfig::from_file(&path).unwrap();
+        assert_eq!(config.client_id, "my-client");
+        assert_eq!(config.token_url, OAuthConfig::default().token_url);
+
+        std::fs::write(&path, "token_url = \"not a url\"\n").unwrap();
+        assert!(OAuthConfig::from_file(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_expires_in_string_or_number() {
+        let response: TokenResponse =
+            serde_json::from_str(r#"{"access_token":"x","expires_in":"3600"}"#).unwrap();
+        assert_eq!(response.expires_in, Some(3600));
+
+        let response: TokenResponse =
+            serde_json::from_str(r#"{"access_token":"x","expires_in":3600}"#).unwrap();
+        assert_eq!(response.expires_in, Some(3600));
+
+        let response: TokenResponse = serde_json::from_str(r#"{"access_token":"x"}"#).unwrap();
+        assert_eq!(response.expires_in, None);
+    }
+
+    #[cfg(any(feature = "async", feature = "blocking", feature = "minimal-http"))]
+    #[test]
+    fn test_parse_json_non_json_body() {
+        let html = "<html><body>Proxy error</body></html>";
+        let err = parse_json::<TokenResponse>(html, OpenAIAuthError::TokenExchange).unwrap_err();
+        assert!(matches!(
+            err,
+            OpenAIAuthError::TokenExchange(msg) if msg.contains("<html><body>Proxy error")
+        ));
+
+        let err = parse_json::<TokenResponse>(html, OpenAIAuthError::TokenRefresh).unwrap_err();
+        assert!(matches!(err, OpenAIAuthError::TokenRefresh(_)));
+
+        let err = parse_json::<TokenResponse>(r#"{"expires_in": 3600}"#, OpenAIAuthError::OAuth)
+            .unwrap_err();
+        assert!(matches!(err, OpenAIAuthError::Serialization(_)));
+    }
+
+    #[test]
+    fn test_pkce_verifier_bytes() {
+        let (_, verifier) = generate_pkce_pair(&OAuthConfig::default()).unwrap();
+        assert_eq!(verifier.len(), 43);
+
+        let config = OAuthConfig::builder().pkce_verifier_bytes(96).build();
+        let (challenge, verifier) = generate_pkce_pair(&config).unwrap();
+        assert_eq!(verifier.len(), 128);
+        assert_eq!(challenge, pkce_challenge(&verifier));
+        validate_verifier(&verifier).unwrap();
+
+        for bytes in [31, 97] {
+            let config = OAuthConfig::builder().pkce_verifier_bytes(bytes).build();
+            assert!(matches!(
+                crate::AuthorizationUrlBuilder::new(&config).build(),
+                Err(OpenAIAuthError::InvalidConfig(e)) if e.contains("pkce_verifier_bytes")
+            ));
+            assert!(config.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_validate_verifier() {
+        let (_, verifier) = generate_pkce_pair(&OAuthConfig::default()).unwrap();
+        assert!(validate_verifier(&verifier).is_ok());
+        assert!(validate_verifier("too-short").is_err());
+        assert!(validate_verifier(&"a".repeat(129)).is_err());
+        assert!(validate_verifier(&format!("{}+", "a".repeat(42))).is_err());
+    }
+
+    #[test]
+    fn test_sanitize_code() {
+        assert_eq!(sanitize_code("  ac_abc123\n"), "ac_abc123");
+        assert_eq!(sanitize_code("#ac_abc123"), "ac_abc123");
+        assert_eq!(sanitize_code("ac_abc%2B123%3D"), "ac_abc+123=");
+        assert_eq!(
+            sanitize_code(" ?code=ac_abc%2F123&scope=openid&state=xyz#_=_ "),
+            "ac_abc/123"
+        );
+        assert_eq!(sanitize_code("error_code=nope&code=ac_abc123"), "ac_abc123");
+        assert_eq!(
+            sanitize_code("http://localhost:1455/auth/callback?state=xyz&code=ac_abc123"),
+            "ac_abc123"
+        );
+    }
 }